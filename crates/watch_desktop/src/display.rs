@@ -0,0 +1,80 @@
+use minifb::Window;
+use watch_lib::{BoundingRect, DisplaySink, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub struct MinifbSink {
+    window: Window,
+    pixels: Vec<u32>,
+}
+
+impl MinifbSink {
+    pub fn new(window: Window) -> MinifbSink {
+        MinifbSink {
+            window,
+            pixels: vec![0; SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize],
+        }
+    }
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+    pub fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+    fn copy_pixel(&mut self, screen_buffer: &[u8], i: usize) {
+        let color = get_pixel_by_index(screen_buffer, i);
+        self.pixels[i] = if color == 0 { 0 } else { u32::MAX };
+    }
+    fn show(&mut self) -> Result<(), minifb::Error> {
+        self.window
+            .update_with_buffer(&self.pixels, SCREEN_WIDTH.into(), SCREEN_HEIGHT.into())
+    }
+}
+
+impl DisplaySink for MinifbSink {
+    type Error = minifb::Error;
+    fn full_update(&mut self, screen_buffer: &[u8]) -> Result<(), minifb::Error> {
+        for i in 0..self.pixels.len() {
+            self.copy_pixel(screen_buffer, i);
+        }
+        self.show()
+    }
+    fn partial_update(
+        &mut self,
+        screen_buffer: &[u8],
+        windows: &[BoundingRect],
+    ) -> Result<(), minifb::Error> {
+        for window in windows {
+            for y in window.y..window.y + window.height as i16 {
+                for x in window.x..window.x + window.width as i16 {
+                    self.copy_pixel(
+                        screen_buffer,
+                        y as usize * SCREEN_WIDTH as usize + x as usize,
+                    );
+                }
+            }
+        }
+        self.show()
+    }
+}
+
+fn get_pixel_by_index(buffer: &[u8], i: usize) -> u8 {
+    if i >= SCREEN_HEIGHT as usize * SCREEN_WIDTH as usize {
+        return 0;
+    }
+
+    let x = i % (SCREEN_WIDTH as usize);
+    let y = i / (SCREEN_WIDTH as usize);
+
+    let byte_index = y * (SCREEN_WIDTH as usize / 8) + (x / 8);
+    let bit_index = 7 - (x % 8);
+
+    if byte_index >= buffer.len() {
+        return 0;
+    }
+
+    let byte_value = buffer[byte_index];
+    if (byte_value >> bit_index) & 1 == 1 {
+        1
+    } else {
+        0
+    }
+}
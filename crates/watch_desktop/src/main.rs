@@ -1,8 +1,9 @@
-use font8x8::{self};
-use minifb;
+mod display;
+
+use display::MinifbSink;
 use watch_lib::{
-    self, BoundingRect, Observable, RectUIElement, SCREEN_HEIGHT, SCREEN_WIDTH, Signal,
-    TextUIElement, UIContext, UIElement, derived, derived2,
+    self, Align, BoundingRect, DisplaySink, ElementId, Font8x8, Layout, Observable, RectUIElement,
+    SCREEN_HEIGHT, SCREEN_WIDTH, Signal, TextUIElement, UIContext, derived, derived2,
 };
 
 fn main() {
@@ -53,10 +54,10 @@ fn main() {
         if c % 2 == 0 { "on" } else { "off" }.to_string()
     });
 
-    let mut ui_context = UIContext::new(font8x8::unicode::BasicFonts::new());
+    let mut ui_context = UIContext::new(&Font8x8);
     // TODO: figure out why it doesn't let this be inlined
     let parent_id = ui_context.mount(
-        0,
+        ElementId::ROOT,
         RectUIElement::new(
             BoundingRect {
                 x: 80,
@@ -67,17 +68,18 @@ fn main() {
             1,
         ),
     );
+    ui_context.set_layout(
+        parent_id,
+        Some(Layout {
+            padding: 6,
+            horizontal: Align::Center,
+            ..Layout::column()
+        }),
+    );
+    // Laid out by the parent, so it doesn't need a rect of its own
     ui_context.mount(
         parent_id,
-        TextUIElement::new(
-            &toggled,
-            BoundingRect {
-                x: 10,
-                y: 0,
-                width: 64,
-                height: 20,
-            },
-        ),
+        TextUIElement::new(&toggled, BoundingRect::default()),
     );
 
     // set_pixel(&mut screen_buffer, 1, 1, 1);
@@ -85,40 +87,15 @@ fn main() {
 
     // draw_text(&mut screen_buffer, &font, "ayy lmao", 1, 11, 0);
 
-    while window.is_open() && !window.is_key_down(minifb::Key::Escape) {
+    let mut display = MinifbSink::new(window);
+    while display.window().is_open() && !display.window().is_key_down(minifb::Key::Escape) {
         counter.set(counter.peek() + 1);
-        ui_context.handle_draw_requests();
-        let mut final_buffer: Vec<u32> = vec![0; SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize];
-        for i in 0..final_buffer.len() {
-            let color = get_pixel_by_index(ui_context.get_screen_buffer(), i);
-            final_buffer[i] = if color == 0 { 0 } else { u32::MAX };
+        let report = ui_context.handle_draw_requests();
+        if report.is_empty() {
+            // Nothing changed, but the window still needs pumping for input
+            display.window_mut().update();
+            continue;
         }
-
-        window
-            .update_with_buffer(&final_buffer, SCREEN_WIDTH.into(), SCREEN_HEIGHT.into())
-            .unwrap();
-    }
-}
-
-fn get_pixel_by_index(buffer: &[u8], i: usize) -> u8 {
-    if i >= SCREEN_HEIGHT as usize * SCREEN_WIDTH as usize {
-        return 0;
-    }
-
-    let x = i % (SCREEN_WIDTH as usize);
-    let y = i / (SCREEN_WIDTH as usize);
-
-    let byte_index = (y * (SCREEN_WIDTH as usize / 8) + (x / 8)) as usize;
-    let bit_index = 7 - (x % 8);
-
-    if byte_index >= buffer.len() {
-        return 0;
-    }
-
-    let byte_value = buffer[byte_index];
-    if (byte_value >> bit_index) & 1 == 1 {
-        1
-    } else {
-        0
+        display.present(&report).unwrap();
     }
 }
@@ -0,0 +1,124 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi::SpiDevice;
+use epd_waveshare::epd1in54_v2::Epd1in54;
+use epd_waveshare::prelude::{RefreshLut, WaveshareDisplay};
+use watch_lib::{BoundingRect, BufferWindow, DisplaySink, PanelFormat, ROW_BYTES, SCREEN_HEIGHT};
+
+const BUFFER_LEN: usize = ROW_BYTES * SCREEN_HEIGHT as usize;
+
+// Drives the 1.54" Waveshare panel. Full updates use the full-refresh LUT, partial updates write
+// each window into the controller's RAM and then do one quick refresh.
+// The panel's RAM is square like the screen, so windows keep the screen buffer's row stride.
+pub struct EpdSink<SPI, BUSY, DC, RST, DELAY> {
+    epd: Epd1in54<SPI, BUSY, DC, RST, DELAY>,
+    spi: SPI,
+    delay: DELAY,
+    // None when the controller may have lost track of it, like after waking
+    lut: Option<RefreshLut>,
+    asleep: bool,
+    format: PanelFormat,
+    panel_buffer: [u8; BUFFER_LEN],
+    // A window's rows packed back to back, as the controller expects them
+    window_buffer: [u8; BUFFER_LEN],
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> EpdSink<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    pub fn new(
+        epd: Epd1in54<SPI, BUSY, DC, RST, DELAY>,
+        spi: SPI,
+        delay: DELAY,
+        format: PanelFormat,
+    ) -> Self {
+        EpdSink {
+            epd,
+            spi,
+            delay,
+            lut: None,
+            asleep: false,
+            format,
+            panel_buffer: [0; BUFFER_LEN],
+            window_buffer: [0; BUFFER_LEN],
+        }
+    }
+    // Resets the controller out of deep sleep, if it's in it
+    fn wake(&mut self) -> Result<(), SPI::Error> {
+        if self.asleep {
+            self.epd.wake_up(&mut self.spi, &mut self.delay)?;
+            self.asleep = false;
+            self.lut = None;
+        }
+        Ok(())
+    }
+    fn use_lut(&mut self, lut: RefreshLut) -> Result<(), SPI::Error> {
+        if self.lut != Some(lut) {
+            self.epd
+                .set_lut(&mut self.spi, &mut self.delay, Some(lut))?;
+            self.lut = Some(lut);
+        }
+        Ok(())
+    }
+    // Put the panel into deep sleep between updates - e-paper ghosts if left powered. The next update wakes
+    // it again.
+    pub fn sleep(&mut self) -> Result<(), SPI::Error> {
+        if !self.asleep {
+            self.epd.sleep(&mut self.spi, &mut self.delay)?;
+            self.asleep = true;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> DisplaySink for EpdSink<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayNs,
+{
+    type Error = SPI::Error;
+    fn full_update(&mut self, screen_buffer: &[u8]) -> Result<(), SPI::Error> {
+        self.wake()?;
+        self.use_lut(RefreshLut::Full)?;
+        self.format.convert(screen_buffer, &mut self.panel_buffer);
+        self.epd
+            .update_frame(&mut self.spi, &self.panel_buffer, &mut self.delay)?;
+        self.epd.display_frame(&mut self.spi, &mut self.delay)
+    }
+    fn partial_update(
+        &mut self,
+        screen_buffer: &[u8],
+        windows: &[BoundingRect],
+    ) -> Result<(), SPI::Error> {
+        self.wake()?;
+        self.use_lut(RefreshLut::Quick)?;
+        self.format.convert(screen_buffer, &mut self.panel_buffer);
+        for window in windows {
+            let window = self.format.convert_window(window);
+            let mut len = 0;
+            for range in BufferWindow::covering(&window).row_byte_ranges() {
+                let row_len = range.len();
+                self.window_buffer[len..len + row_len].copy_from_slice(&self.panel_buffer[range]);
+                len += row_len;
+            }
+            self.epd.update_partial_frame(
+                &mut self.spi,
+                &mut self.delay,
+                &self.window_buffer[..len],
+                window.x as u32,
+                window.y as u32,
+                window.width as u32,
+                window.height as u32,
+            )?;
+        }
+        self.epd.display_frame(&mut self.spi, &mut self.delay)
+    }
+}
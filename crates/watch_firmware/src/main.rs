@@ -1,13 +1,46 @@
 #![no_std]
 #![no_main]
 
+extern crate alloc;
+
+mod display;
+
+use alloc::format;
+use display::EpdSink;
 use embassy_executor::Spawner;
 use embassy_nrf::gpio::{Input, Level, Output, OutputDrive, Pull};
 use embassy_nrf::spim;
-use embassy_time::Timer;
+use embassy_time::{Instant, Timer};
+use embedded_alloc::LlffHeap as Heap;
 use embedded_hal_bus::spi::ExclusiveDevice;
 use epd_waveshare::{self, prelude::WaveshareDisplay};
+use watch_lib::{
+    BoundingRect, DisplaySink, ElementId, Font8x8, PanelFormat, RectUIElement, Rotation, Signal,
+    TextUIElement, UIContext, derived,
+};
 use {defmt_rtt as _, panic_probe as _};
+
+// The panel uses 0 for black ink and is mounted the same way up as its RAM
+const PANEL_FORMAT: PanelFormat = PanelFormat {
+    invert: true,
+    rotation: Rotation::Deg0,
+};
+
+// Elements, signal state and strings live here. UIContext's own storage is fixed-capacity, but text
+// and shape elements keep what they've rendered here too, which drawing allocates the first time the
+// element is drawn and whenever it grows. Leave room for a byte per 8 pixels of text and two per 8 of
+// shape.
+const HEAP_SIZE: usize = 16 * 1024;
+
+#[global_allocator]
+static HEAP: Heap = Heap::empty();
+
+fn init_heap() {
+    use core::mem::MaybeUninit;
+    static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
+    // Safety: called once at startup, before anything allocates
+    unsafe { HEAP.init(&raw mut HEAP_MEM as usize, HEAP_SIZE) }
+}
 embassy_nrf::bind_interrupts!(struct Irqs {
     SPI2 => spim::InterruptHandler<embassy_nrf::peripherals::SPI2>;
 });
@@ -43,38 +76,74 @@ async fn main(_spawner: Spawner) {
     let mut delay = embassy_time::Delay;
 
     led_red.set_low();
+    init_heap();
     let mut epd =
         epd_waveshare::epd1in54_v2::Epd1in54::new(&mut spi_device, busy, dc, rst, &mut delay, None)
             .unwrap();
 
-    let mut buffer = [0x00u8; 5000]; // 0xFF is White
-
-    // Pixel (x:0, y:0) is the highest bit of the first byte.
-    // 0 is Black, 1 is White.
-    buffer[0] = 0x7F; // 0111 1111 (First pixel black)
-    buffer[10] = 0xFF;
-    buffer[2000] = 0xFF;
-    buffer[3333] = 0xFF;
-
     led_green.set_low();
-    // Push buffer to display
-    epd.clear_frame(&mut spi_device, &mut delay);
-    epd.update_frame(&mut spi_device, &buffer, &mut delay)
-        .unwrap();
-    epd.display_frame(&mut spi_device, &mut delay).unwrap();
+    epd.clear_frame(&mut spi_device, &mut delay).unwrap();
+    let mut display = EpdSink::new(epd, spi_device, delay, PANEL_FORMAT);
+
+    let uptime_minutes = Signal::new(0u64);
+    let mut ui_context = UIContext::new(&Font8x8);
+    mount_watch_face(&mut ui_context, &uptime_minutes);
+    // The panel was just cleared, so repaint everything from the root
+    ui_context
+        .elements_requesting_redraw
+        .borrow_mut()
+        .insert(ElementId::ROOT);
 
     led_blue.set_low();
-    // TODO: remember epd.sleep to prevent ghosting
 
     loop {
-        led_red.set_high();
-        Timer::after_millis(1000).await;
-        led_red.set_low();
+        uptime_minutes.set(Instant::now().as_secs() / 60);
+        display.present(&ui_context.handle_draw_requests()).unwrap();
+        display.sleep().unwrap();
+        led_red.toggle();
         Timer::after_millis(1000).await;
-        epd.update_frame(&mut spi_device, &buffer, &mut delay)
-            .unwrap();
-        epd.display_frame(&mut spi_device, &mut delay).unwrap();
-        Timer::after_millis(2000).await;
-        //  epd.clear_frame(&mut spi_device, &mut delay);
     }
 }
+
+// No RTC yet, so the face counts hours and minutes since boot
+fn mount_watch_face(ui_context: &mut UIContext, uptime_minutes: &Signal<u64>) {
+    let time_text = derived(uptime_minutes, |minutes| {
+        format!("{:02}:{:02}", (minutes / 60) % 24, minutes % 60)
+    });
+    let frame_id = ui_context.mount(
+        ElementId::ROOT,
+        RectUIElement::new(
+            BoundingRect {
+                x: 40,
+                y: 80,
+                width: 120,
+                height: 40,
+            },
+            1,
+        ),
+    );
+    let face_id = ui_context.mount(
+        frame_id,
+        RectUIElement::new(
+            BoundingRect {
+                x: 2,
+                y: 2,
+                width: 116,
+                height: 36,
+            },
+            0,
+        ),
+    );
+    ui_context.mount(
+        face_id,
+        TextUIElement::new(
+            &time_text,
+            BoundingRect {
+                x: 38,
+                y: 14,
+                width: 40,
+                height: 8,
+            },
+        ),
+    );
+}
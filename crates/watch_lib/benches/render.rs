@@ -0,0 +1,164 @@
+// Host benchmark for full-screen redraws. Run with `cargo bench -p watch_lib`.
+use std::time::{Duration, Instant};
+use watch_lib::{
+    BoundingRect, CapacityError, ElementId, Font8x8, Observable, RectUIElement, SCREEN_HEIGHT,
+    SCREEN_WIDTH, Signal, TextStyle, TextUIElement, UIContext, UIElement, Wrap,
+};
+
+const FRAMES: u32 = 200;
+
+const FULL_SCREEN: BoundingRect = BoundingRect {
+    x: 0,
+    y: 0,
+    width: SCREEN_WIDTH,
+    height: SCREEN_HEIGHT,
+};
+
+// Only implements get_pixel, so it exercises the per-pixel fallback
+struct PixelRectUIElement {
+    rect: BoundingRect,
+    color: u8,
+    next_element_id: Option<ElementId>,
+}
+
+impl UIElement for PixelRectUIElement {
+    fn mount_to_context(&self, _ctx: &UIContext, _id: ElementId) -> Result<(), CapacityError> {
+        Ok(())
+    }
+    fn get_pixel(&self, _ctx: &UIContext, _x: u8, _y: u8) -> u8 {
+        self.color
+    }
+    fn get_bounding_rect(&self) -> BoundingRect {
+        self.rect
+    }
+    fn get_first_child_id(&self) -> Option<ElementId> {
+        None
+    }
+    fn get_next_element_id(&self) -> Option<ElementId> {
+        self.next_element_id
+    }
+    fn set_next_element_id(&mut self, id: Option<ElementId>) {
+        self.next_element_id = id
+    }
+    fn insert_child_at_end(&mut self, _ctx: &mut UIContext, _id: ElementId) {
+        panic!("PixelRectUIElement does not support children");
+    }
+}
+
+fn new_context() -> UIContext {
+    UIContext::new(&Font8x8)
+}
+
+fn time_full_redraws(ctx: &mut UIContext, id: ElementId) -> Duration {
+    // Warm up so the first frame's allocations aren't counted
+    ctx.elements_requesting_redraw.borrow_mut().insert(id);
+    ctx.handle_draw_requests();
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        ctx.elements_requesting_redraw.borrow_mut().insert(id);
+        ctx.handle_draw_requests();
+    }
+    start.elapsed() / FRAMES
+}
+
+// Like time_full_redraws, but the text changes every frame so nothing can be reused
+fn time_changing_text(ctx: &mut UIContext, text: &Signal<String>) -> Duration {
+    let values = [text.peek(), text.peek().to_uppercase()];
+    ctx.handle_draw_requests();
+    let start = Instant::now();
+    for frame in 0..FRAMES {
+        text.set(values[frame as usize % 2].clone());
+        ctx.handle_draw_requests();
+    }
+    start.elapsed() / FRAMES
+}
+
+fn report(name: &str, per_frame: Duration) {
+    println!(
+        "{name:<32} {:>10.1} us/frame",
+        per_frame.as_secs_f64() * 1e6
+    );
+}
+
+fn main() {
+    let mut ctx = new_context();
+    let id = ctx.mount(
+        ElementId::ROOT,
+        PixelRectUIElement {
+            rect: FULL_SCREEN,
+            color: 1,
+            next_element_id: None,
+        },
+    );
+    let per_pixel = time_full_redraws(&mut ctx, id);
+    report("rect via get_pixel fallback", per_pixel);
+
+    let mut ctx = new_context();
+    let id = ctx.mount(ElementId::ROOT, RectUIElement::new(FULL_SCREEN, 1));
+    let spans = time_full_redraws(&mut ctx, id);
+    report("rect via draw_row spans", spans);
+    println!(
+        "{:<32} {:>10.1}x",
+        "speedup",
+        per_pixel.as_secs_f64() / spans.as_secs_f64()
+    );
+
+    let mut ctx = new_context();
+    let text = Signal::new("The quick brown fox jumps".to_string());
+    let id = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::new(
+            &text,
+            BoundingRect {
+                x: 0,
+                y: 0,
+                width: SCREEN_WIDTH,
+                height: 8,
+            },
+        ),
+    );
+    report("one line of text", time_full_redraws(&mut ctx, id));
+
+    let mut ctx = new_context();
+    let paragraph = Signal::new("The quick brown fox jumps over the lazy dog. ".repeat(12));
+    let style = TextStyle {
+        wrap: Wrap::Word,
+        ..TextStyle::default()
+    };
+    let id = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::with_style(&paragraph, FULL_SCREEN, style),
+    );
+    report(
+        "full screen of wrapped text",
+        time_full_redraws(&mut ctx, id),
+    );
+    report(
+        "wrapped text changing",
+        time_changing_text(&mut ctx, &paragraph),
+    );
+
+    // Reading a 64x8 label a pixel at a time
+    let ctx = new_context();
+    let label = Signal::new("12:34:56".to_string());
+    let element = TextUIElement::new(
+        &label,
+        BoundingRect {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 8,
+        },
+    );
+    let start = Instant::now();
+    let mut set = 0u32;
+    for _ in 0..FRAMES {
+        for y in 0..8 {
+            for x in 0..64 {
+                set += element.get_pixel(&ctx, x, y) as u32;
+            }
+        }
+    }
+    std::hint::black_box(set);
+    report("64x8 label via get_pixel", start.elapsed() / FRAMES);
+}
@@ -0,0 +1,269 @@
+use crate::{BitmapFont, BitmapGlyph, MAX_GLYPH_WIDTH};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+// Why a BDF file couldn't be read, and the line it went wrong on, counting from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BdfError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for BdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// A BDF font turned into BitmapFont tables. Use it as it is on the host, or write it out as Rust source
+// for the firmware to build in.
+#[derive(Clone, Debug)]
+pub struct BdfFont {
+    pub name: String,
+    pub height: u8,
+    pub baseline: u8,
+    pub default_char: char,
+    pub glyphs: Vec<BitmapGlyph>,
+    pub bitmap: Vec<u8>,
+}
+
+#[derive(Default)]
+struct RawGlyph {
+    // None for glyphs without a Unicode encoding
+    c: Option<char>,
+    advance: i32,
+    // Width, height, and offset from the origin to the bottom left corner, with y going up
+    bbx: [i32; 4],
+    // Leftmost pixel in the top bit
+    rows: Vec<u32>,
+}
+
+fn error(line: usize, message: &'static str) -> BdfError {
+    BdfError { line, message }
+}
+
+fn numbers<const N: usize>(
+    line: usize,
+    mut words: core::str::SplitWhitespace,
+) -> Result<[i32; N], BdfError> {
+    let mut values = [0; N];
+    for value in values.iter_mut() {
+        *value = words
+            .next()
+            .and_then(|word| word.parse().ok())
+            .ok_or(error(line, "expected a number"))?;
+    }
+    Ok(values)
+}
+
+// Packs a row of hex digits into the top of a u32, dropping anything past 32 pixels
+fn bitmap_row(line: usize, hex: &str) -> Result<u32, BdfError> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(error(line, "bitmap rows should be whole bytes"));
+    }
+    let mut row = 0;
+    for (i, byte) in hex.as_bytes().chunks(2).enumerate() {
+        let byte = core::str::from_utf8(byte)
+            .ok()
+            .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            .ok_or(error(line, "bitmap rows should be hex"))?;
+        if i < 4 {
+            row |= (byte as u32) << (24 - i * 8);
+        }
+    }
+    Ok(row)
+}
+
+impl BdfFont {
+    pub fn parse(source: &str) -> Result<BdfFont, BdfError> {
+        let mut name = String::new();
+        let mut bounding_box = [0; 4];
+        let (mut ascent, mut descent, mut default_char) = (None, None, None);
+        let mut raw_glyphs = Vec::new();
+        let mut glyph: Option<RawGlyph> = None;
+        let mut in_bitmap = false;
+        let mut line = 0;
+
+        for (index, text) in source.lines().enumerate() {
+            line = index + 1;
+            let mut words = text.split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            if in_bitmap && keyword != "ENDCHAR" {
+                let glyph = glyph.as_mut().unwrap();
+                glyph.rows.push(bitmap_row(line, keyword)?);
+                continue;
+            }
+            match keyword {
+                "FONT" => name = String::from(text["FONT".len()..].trim()),
+                "FONTBOUNDINGBOX" => bounding_box = numbers(line, words)?,
+                "FONT_ASCENT" => ascent = Some(numbers::<1>(line, words)?[0]),
+                "FONT_DESCENT" => descent = Some(numbers::<1>(line, words)?[0]),
+                "DEFAULT_CHAR" => default_char = Some(numbers::<1>(line, words)?[0]),
+                "STARTCHAR" => {
+                    if glyph.is_some() {
+                        return Err(error(line, "STARTCHAR before the last glyph's ENDCHAR"));
+                    }
+                    glyph = Some(RawGlyph::default());
+                }
+                "ENCODING" | "DWIDTH" | "BBX" | "BITMAP" | "ENDCHAR" => {
+                    let Some(current) = glyph.as_mut() else {
+                        return Err(error(line, "glyph property outside STARTCHAR"));
+                    };
+                    match keyword {
+                        "ENCODING" => {
+                            let encoding = numbers::<1>(line, words)?[0];
+                            current.c = u32::try_from(encoding).ok().and_then(char::from_u32);
+                        }
+                        "DWIDTH" => current.advance = numbers::<1>(line, words)?[0],
+                        "BBX" => current.bbx = numbers(line, words)?,
+                        "BITMAP" => in_bitmap = true,
+                        _ => {
+                            if current.rows.len() != current.bbx[1].max(0) as usize {
+                                return Err(error(line, "bitmap doesn't match the BBX height"));
+                            }
+                            if current.bbx[0] + current.bbx[2].max(0) > MAX_GLYPH_WIDTH as i32 {
+                                return Err(error(line, "glyph is wider than MAX_GLYPH_WIDTH"));
+                            }
+                            in_bitmap = false;
+                            raw_glyphs.push(glyph.take().unwrap());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if glyph.is_some() {
+            return Err(error(line, "missing ENDCHAR"));
+        }
+
+        // Fonts without these properties go by their bounding box, whose offset is to the lowest descender
+        let [_, box_height, _, box_y] = bounding_box;
+        let ascent = ascent.unwrap_or(box_height + box_y);
+        let descent = descent.unwrap_or(-box_y);
+        let height = u8::try_from(ascent + descent)
+            .ok()
+            .filter(|height| *height > 0)
+            .ok_or(error(line, "font height should be 1 to 255 pixels"))?;
+        let baseline = ascent.clamp(0, height as i32) as u8;
+        let default_char = default_char
+            .and_then(|c| char::from_u32(c as u32))
+            .unwrap_or('?');
+
+        let mut font = BdfFont {
+            name,
+            height,
+            baseline,
+            default_char,
+            glyphs: Vec::new(),
+            bitmap: Vec::new(),
+        };
+        raw_glyphs.retain(|raw| raw.c.is_some());
+        // Stable, so the first of any repeated encodings wins
+        raw_glyphs.sort_by_key(|raw| raw.c);
+        raw_glyphs.dedup_by_key(|raw| raw.c);
+        for raw in &raw_glyphs {
+            font.add_glyph(raw, ascent);
+        }
+        Ok(font)
+    }
+
+    // Places the glyph's bitmap in a cell the height of the font, with the baseline at ascent
+    fn add_glyph(&mut self, raw: &RawGlyph, ascent: i32) {
+        let [box_width, box_height, box_x, box_y] = raw.bbx;
+        let width = (box_x.max(0) + box_width).clamp(0, MAX_GLYPH_WIDTH as i32) as u8;
+        let mask = if width == 0 {
+            0
+        } else {
+            u32::MAX << (32 - width)
+        };
+        let box_top = ascent - (box_y + box_height);
+        let mut rows: Vec<(u8, u32)> = Vec::new();
+        for (i, row) in raw.rows.iter().enumerate() {
+            let y = box_top + i as i32;
+            if !(0..self.height as i32).contains(&y) {
+                continue;
+            }
+            // Pixels left of the origin are cut off
+            let row = if box_x >= 0 {
+                row.checked_shr(box_x as u32)
+            } else {
+                row.checked_shl(box_x.unsigned_abs())
+            };
+            let row = row.unwrap_or(0);
+            rows.push((y as u8, row & mask));
+        }
+        // Blank rows above and below the glyph aren't stored
+        let first = rows.iter().position(|(_, row)| *row != 0);
+        let last = rows.iter().rposition(|(_, row)| *row != 0);
+        let rows = match (first, last) {
+            (Some(first), Some(last)) => &rows[first..=last],
+            _ => &[][..],
+        };
+
+        self.glyphs.push(BitmapGlyph {
+            c: raw.c.unwrap(),
+            advance: raw.advance.clamp(0, u8::MAX as i32) as u8,
+            width,
+            top: rows.first().map_or(0, |(y, _)| *y),
+            rows: rows.len() as u8,
+            offset: self.bitmap.len() as u32,
+        });
+        for (_, row) in rows {
+            let bytes = row.to_be_bytes();
+            self.bitmap
+                .extend_from_slice(&bytes[..width.div_ceil(8) as usize]);
+        }
+    }
+
+    pub fn font(&self) -> BitmapFont<'_> {
+        BitmapFont {
+            height: self.height,
+            baseline: self.baseline,
+            default_char: self.default_char,
+            glyphs: &self.glyphs,
+            bitmap: &self.bitmap,
+        }
+    }
+
+    // Source for a static BitmapFont called static_name, for the firmware to include
+    pub fn to_rust(&self, static_name: &str) -> String {
+        let mut out = String::new();
+        // Writing to a String can't fail
+        let _ = self.write_rust(&mut out, static_name);
+        out
+    }
+
+    fn write_rust(&self, out: &mut String, static_name: &str) -> fmt::Result {
+        writeln!(out, "// Generated from the BDF font {}", self.name)?;
+        writeln!(out, "#[rustfmt::skip]")?;
+        writeln!(
+            out,
+            "pub static {static_name}: watch_lib::BitmapFont<'static> = watch_lib::BitmapFont {{"
+        )?;
+        writeln!(out, "    height: {},", self.height)?;
+        writeln!(out, "    baseline: {},", self.baseline)?;
+        writeln!(out, "    default_char: {:?},", self.default_char)?;
+        writeln!(out, "    glyphs: &[")?;
+        for glyph in &self.glyphs {
+            writeln!(
+                out,
+                "        watch_lib::BitmapGlyph {{ c: {:?}, advance: {}, width: {}, top: {}, rows: {}, offset: {} }},",
+                glyph.c, glyph.advance, glyph.width, glyph.top, glyph.rows, glyph.offset
+            )?;
+        }
+        writeln!(out, "    ],")?;
+        writeln!(out, "    bitmap: &[")?;
+        for chunk in self.bitmap.chunks(16) {
+            write!(out, "       ")?;
+            for byte in chunk {
+                write!(out, " 0x{byte:02x},")?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "    ],")?;
+        writeln!(out, "}};")
+    }
+}
@@ -0,0 +1,23 @@
+// Prints a BDF font as a BitmapFont static, for fonts the firmware builds in:
+//   cargo run -p watch_lib --bin bdf_to_rust -- font.bdf SMALL_FONT > small_font.rs
+use std::{env, fs, process};
+use watch_lib::bdf::BdfFont;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let [_, path, static_name] = args.as_slice() else {
+        eprintln!("usage: bdf_to_rust <font.bdf> <STATIC_NAME>");
+        process::exit(2);
+    };
+    let source = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("{path}: {e}");
+        process::exit(1);
+    });
+    match BdfFont::parse(&source) {
+        Ok(font) => print!("{}", font.to_rust(static_name)),
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            process::exit(1);
+        }
+    }
+}
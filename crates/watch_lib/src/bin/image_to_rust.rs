@@ -0,0 +1,41 @@
+// Prints a PBM, XBM or PNG image as a Bitmap const, for icons the firmware builds in. PNGs can be
+// dithered rather than thresholded:
+//   cargo run -p watch_lib --bin image_to_rust -- photo.png PHOTO floyd-steinberg > photo.rs
+use std::{env, fs, process};
+use watch_lib::Dither;
+use watch_lib::image_file::ImageFile;
+
+const USAGE: &str = "usage: image_to_rust <image.pbm|xbm|png> <CONST_NAME> [threshold|floyd-steinberg|atkinson|bayer]";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (path, const_name, method) = match args.as_slice() {
+        [_, path, const_name] => (path, const_name, "threshold"),
+        [_, path, const_name, method] => (path, const_name, method.as_str()),
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    let method = match method {
+        "threshold" => Dither::Threshold,
+        "floyd-steinberg" => Dither::FloydSteinberg,
+        "atkinson" => Dither::Atkinson,
+        "bayer" => Dither::Bayer,
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    let bytes = fs::read(path).unwrap_or_else(|e| {
+        eprintln!("{path}: {e}");
+        process::exit(1);
+    });
+    match ImageFile::parse_dithered(&bytes, method) {
+        Ok(image) => print!("{}", image.to_rust(const_name)),
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            process::exit(1);
+        }
+    }
+}
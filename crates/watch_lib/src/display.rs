@@ -0,0 +1,174 @@
+use crate::{BoundingRect, BufferWindow, DrawReport, ROW_BYTES, SCREEN_HEIGHT, SCREEN_WIDTH};
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::convert::Infallible;
+
+// Something that can show the screen buffer - the desktop window, the e-paper panel, or a test recorder.
+// Buffers are always in UIContext's packed format: one bit per pixel, MSB leftmost, 1 = set.
+pub trait DisplaySink {
+    type Error;
+    fn full_update(&mut self, screen_buffer: &[u8]) -> Result<(), Self::Error>;
+    // Windows are non-overlapping screen rects. Sinks only need to copy those parts of the buffer.
+    fn partial_update(
+        &mut self,
+        screen_buffer: &[u8],
+        windows: &[BoundingRect],
+    ) -> Result<(), Self::Error>;
+    fn present(&mut self, report: &DrawReport) -> Result<(), Self::Error> {
+        if report.is_empty() {
+            Ok(())
+        } else if report.full_refresh {
+            self.full_update(report.screen_buffer)
+        } else {
+            self.partial_update(report.screen_buffer, report.aligned_regions)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordedUpdate {
+    Full,
+    Partial(Vec<BoundingRect>),
+}
+
+// Keeps its own copy of the frame, only taking the bytes each update says changed
+pub struct RecordingSink {
+    frame: Vec<u8>,
+    updates: Vec<RecordedUpdate>,
+}
+
+impl RecordingSink {
+    pub fn new() -> RecordingSink {
+        RecordingSink {
+            frame: alloc::vec![0; ROW_BYTES * SCREEN_HEIGHT as usize],
+            updates: Vec::new(),
+        }
+    }
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+    pub fn updates(&self) -> &[RecordedUpdate] {
+        &self.updates
+    }
+}
+
+impl Default for RecordingSink {
+    fn default() -> Self {
+        RecordingSink::new()
+    }
+}
+
+impl DisplaySink for RecordingSink {
+    type Error = Infallible;
+    fn full_update(&mut self, screen_buffer: &[u8]) -> Result<(), Infallible> {
+        self.frame.copy_from_slice(screen_buffer);
+        self.updates.push(RecordedUpdate::Full);
+        Ok(())
+    }
+    fn partial_update(
+        &mut self,
+        screen_buffer: &[u8],
+        windows: &[BoundingRect],
+    ) -> Result<(), Infallible> {
+        for window in windows {
+            for range in BufferWindow::covering(window).row_byte_ranges() {
+                self.frame[range.clone()].copy_from_slice(&screen_buffer[range]);
+            }
+        }
+        self.updates.push(RecordedUpdate::Partial(windows.to_vec()));
+        Ok(())
+    }
+}
+
+// Clockwise rotation from screen space into the panel's RAM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+// How a panel wants its frame laid out, relative to the screen buffer. The packing itself
+// (rows of SCREEN_WIDTH / 8 bytes, MSB leftmost) already matches, since the width is a multiple of 8 -
+// what differs is which way up the panel is mounted and what a set bit means.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PanelFormat {
+    // Panels like the SSD1681 use 0 for black ink, where a set pixel in the screen buffer is 1
+    pub invert: bool,
+    pub rotation: Rotation,
+}
+
+impl PanelFormat {
+    pub fn panel_width(&self) -> u8 {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => SCREEN_WIDTH,
+            Rotation::Deg90 | Rotation::Deg270 => SCREEN_HEIGHT,
+        }
+    }
+    pub fn panel_height(&self) -> u8 {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => SCREEN_HEIGHT,
+            Rotation::Deg90 | Rotation::Deg270 => SCREEN_WIDTH,
+        }
+    }
+    // Rewrites the whole screen buffer into panel_buffer, which must be the same length
+    pub fn convert(&self, screen_buffer: &[u8], panel_buffer: &mut [u8]) {
+        let flip = if self.invert { 0xFF } else { 0x00 };
+        match self.rotation {
+            Rotation::Deg0 => {
+                for (out, byte) in panel_buffer.iter_mut().zip(screen_buffer) {
+                    *out = byte ^ flip;
+                }
+            }
+            // Whole bytes map onto whole bytes, just mirrored
+            Rotation::Deg180 => {
+                for (out, byte) in panel_buffer.iter_mut().zip(screen_buffer.iter().rev()) {
+                    *out = byte.reverse_bits() ^ flip;
+                }
+            }
+            Rotation::Deg90 | Rotation::Deg270 => {
+                panel_buffer.fill(flip);
+                let panel_row_bytes = (self.panel_width() as usize).div_ceil(8);
+                for y in 0..SCREEN_HEIGHT {
+                    for x in 0..SCREEN_WIDTH {
+                        let idx = y as usize * ROW_BYTES + x as usize / 8;
+                        if screen_buffer[idx] & (0x80 >> (x % 8)) == 0 {
+                            continue;
+                        }
+                        let (px, py) = self.panel_point(x, y);
+                        panel_buffer[py as usize * panel_row_bytes + px as usize / 8] ^=
+                            0x80 >> (px % 8);
+                    }
+                }
+            }
+        }
+    }
+    fn panel_point(&self, x: u8, y: u8) -> (u8, u8) {
+        match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (SCREEN_HEIGHT - 1 - y, x),
+            Rotation::Deg180 => (SCREEN_WIDTH - 1 - x, SCREEN_HEIGHT - 1 - y),
+            Rotation::Deg270 => (y, SCREEN_WIDTH - 1 - x),
+        }
+    }
+    // Where an on-screen window lands on the panel, widened back out to whole bytes
+    pub fn convert_window(&self, window: &BoundingRect) -> BoundingRect {
+        let x1 = (window.x + window.width as i16 - 1) as u8;
+        let y1 = (window.y + window.height as i16 - 1) as u8;
+        let (ax, ay) = self.panel_point(window.x as u8, window.y as u8);
+        let (bx, by) = self.panel_point(x1, y1);
+        let x0 = min(ax, bx) / 8 * 8;
+        let x1 = min(
+            (max(ax, bx) as usize + 1).div_ceil(8) * 8,
+            self.panel_width() as usize,
+        );
+        let y0 = min(ay, by);
+        BoundingRect {
+            x: x0 as i16,
+            y: y0 as i16,
+            width: (x1 - x0 as usize) as u8,
+            height: max(ay, by) - y0 + 1,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// Turns 8-bit grayscale, 0 black to 255 white, into 1bpp packed like Bitmap and the screen buffer, with
+// dark pixels set. Works on the watch too: the error diffusion rows live on the stack, not the heap.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+    // Each pixel on its own, dark if it's below half. Keeps line art and text crisp.
+    #[default]
+    Threshold,
+    // Passes each pixel's rounding error on to the pixels right and below it. Smoothest for photos.
+    FloydSteinberg,
+    // Like FloydSteinberg but only passes on 3/4 of the error, trading shadow and highlight detail for
+    // more contrast. Suits small icons.
+    Atkinson,
+    // Compares against a repeating 8x8 pattern. Noisier looking, but each pixel only depends on its own
+    // value, so a picture that changes in places dithers the same everywhere else.
+    Bayer,
+}
+
+// Each value is the step of gray where that pixel turns from dark to light
+#[rustfmt::skip]
+const BAYER: [[u8; 8]; 8] = [
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+// Room for the widest bitmap, plus 2 pixels either side for error that falls off the edges
+const ERROR_ROW: usize = u8::MAX as usize + 4;
+
+// Dithers a width by height grayscale picture into out, which needs width.div_ceil(8) bytes a row
+pub fn dither(gray: &[u8], width: u8, height: u8, method: Dither, out: &mut [u8]) {
+    let (width, height) = (width as usize, height as usize);
+    let row_bytes = width.div_ceil(8);
+    assert!(gray.len() >= width * height, "not enough gray pixels");
+    assert!(
+        out.len() >= row_bytes * height,
+        "not enough room to dither into"
+    );
+    out[..row_bytes * height].fill(0);
+    // Error still to come for this row and the next two, offset by 2 so x - 1 and x + 2 are in range
+    let mut errors = [[0i16; ERROR_ROW]; 3];
+    for y in 0..height {
+        for x in 0..width {
+            let value = gray[y * width + x] as i16 + errors[0][x + 2];
+            let dark = match method {
+                Dither::Bayer => value < BAYER[y % 8][x % 8] as i16 * 4 + 2,
+                _ => value < 0x80,
+            };
+            if dark {
+                out[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+            }
+            let error = value - if dark { 0 } else { 0xFF };
+            match method {
+                Dither::FloydSteinberg => {
+                    errors[0][x + 3] += error * 7 / 16;
+                    errors[1][x + 1] += error * 3 / 16;
+                    errors[1][x + 2] += error * 5 / 16;
+                    errors[1][x + 3] += error / 16;
+                }
+                Dither::Atkinson => {
+                    let eighth = error / 8;
+                    errors[0][x + 3] += eighth;
+                    errors[0][x + 4] += eighth;
+                    errors[1][x + 1] += eighth;
+                    errors[1][x + 2] += eighth;
+                    errors[1][x + 3] += eighth;
+                    errors[2][x + 2] += eighth;
+                }
+                Dither::Threshold | Dither::Bayer => {}
+            }
+        }
+        errors.rotate_left(1);
+        errors[2] = [0; ERROR_ROW];
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(not(feature = "fixed-capacity"))]
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "fixed-capacity")]
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+
+// Returned instead of allocating when a fixed-capacity collection is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("fixed capacity exceeded")
+    }
+}
+
+// A Vec that starts out with room for N items. With the fixed-capacity feature it's an inline array
+// instead, and pushing past N fails rather than allocating.
+pub struct CapacityVec<T, const N: usize> {
+    #[cfg(not(feature = "fixed-capacity"))]
+    items: Vec<T>,
+    #[cfg(feature = "fixed-capacity")]
+    items: [MaybeUninit<T>; N],
+    #[cfg(feature = "fixed-capacity")]
+    len: usize,
+}
+
+#[cfg(not(feature = "fixed-capacity"))]
+impl<T, const N: usize> CapacityVec<T, N> {
+    pub fn new() -> Self {
+        CapacityVec {
+            items: Vec::with_capacity(N),
+        }
+    }
+    pub fn try_push(&mut self, value: T) -> Result<(), CapacityError> {
+        self.items.push(value);
+        Ok(())
+    }
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), CapacityError> {
+        self.items.insert(index, value);
+        Ok(())
+    }
+    pub fn pop(&mut self) -> Option<T> {
+        self.items.pop()
+    }
+    pub fn remove(&mut self, index: usize) -> T {
+        self.items.remove(index)
+    }
+    pub fn truncate(&mut self, len: usize) {
+        self.items.truncate(len);
+    }
+}
+
+#[cfg(feature = "fixed-capacity")]
+impl<T, const N: usize> CapacityVec<T, N> {
+    pub const fn new() -> Self {
+        CapacityVec {
+            items: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+    pub fn try_push(&mut self, value: T) -> Result<(), CapacityError> {
+        if self.len == N {
+            return Err(CapacityError);
+        }
+        self.items[self.len].write(value);
+        self.len += 1;
+        Ok(())
+    }
+    pub fn try_insert(&mut self, index: usize, value: T) -> Result<(), CapacityError> {
+        assert!(index <= self.len, "insertion index out of bounds");
+        if self.len == N {
+            return Err(CapacityError);
+        }
+        // Safety: both ranges are within the array, and everything moved is initialised
+        unsafe {
+            let base = self.items.as_mut_ptr();
+            core::ptr::copy(base.add(index), base.add(index + 1), self.len - index);
+        }
+        self.items[index].write(value);
+        self.len += 1;
+        Ok(())
+    }
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // Safety: the item was initialised, and is no longer counted so won't be read again
+        Some(unsafe { self.items[self.len].assume_init_read() })
+    }
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index out of bounds");
+        // Safety: the item is initialised, and the items after it are shifted down over its slot
+        unsafe {
+            let value = self.items[index].assume_init_read();
+            let base = self.items.as_mut_ptr();
+            core::ptr::copy(base.add(index + 1), base.add(index), self.len - index - 1);
+            self.len -= 1;
+            value
+        }
+    }
+    pub fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.pop();
+        }
+    }
+}
+
+impl<T, const N: usize> CapacityVec<T, N> {
+    // Panics when full in fixed-capacity builds - use try_push where running out is expected
+    pub fn push(&mut self, value: T) {
+        self.try_push(value).expect("CapacityVec is full");
+    }
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        if self.is_empty() {
+            return;
+        }
+        // Swap each new value down past the duplicates, which all end up at the end
+        let mut kept = 1;
+        for i in 1..self.len() {
+            if self[i] != self[kept - 1] {
+                self.swap(i, kept);
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len() {
+            if keep(&self[i]) {
+                self.swap(i, kept);
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+}
+
+impl<T, const N: usize> Default for CapacityVec<T, N> {
+    fn default() -> Self {
+        CapacityVec::new()
+    }
+}
+
+impl<T, const N: usize> Deref for CapacityVec<T, N> {
+    type Target = [T];
+    #[cfg(not(feature = "fixed-capacity"))]
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+    #[cfg(feature = "fixed-capacity")]
+    fn deref(&self) -> &[T] {
+        // Safety: the first len items are initialised
+        unsafe { core::slice::from_raw_parts(self.items.as_ptr() as *const T, self.len) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for CapacityVec<T, N> {
+    #[cfg(not(feature = "fixed-capacity"))]
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.items
+    }
+    #[cfg(feature = "fixed-capacity")]
+    fn deref_mut(&mut self) -> &mut [T] {
+        // Safety: the first len items are initialised
+        unsafe { core::slice::from_raw_parts_mut(self.items.as_mut_ptr() as *mut T, self.len) }
+    }
+}
+
+#[cfg(feature = "fixed-capacity")]
+impl<T, const N: usize> Drop for CapacityVec<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
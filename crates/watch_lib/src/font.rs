@@ -0,0 +1,129 @@
+use font8x8::UnicodeFonts;
+use font8x8::unicode::{
+    BasicFonts, BlockFonts, BoxFonts, GreekFonts, HiraganaFonts, LatinFonts, MiscFonts,
+};
+
+// Widest glyph bitmap a font can hand out, since rows come back as a u32
+pub const MAX_GLYPH_WIDTH: u8 = 32;
+
+// Bitmap glyphs with their metrics. Every glyph is the same height, and lines of text are that far apart.
+pub trait Font {
+    fn height(&self) -> u8;
+    // Rows from the top of a glyph down to the line its text sits on
+    fn baseline(&self) -> u8;
+    // How far along the next glyph starts
+    fn advance(&self, c: char) -> u8;
+    // Columns of the glyph's bitmap, which can run past its advance
+    fn glyph_width(&self, c: char) -> u8 {
+        self.advance(c)
+    }
+    // Row y of the glyph, with the leftmost pixel in the top bit. Characters the font doesn't have should
+    // still come back as something, even if it's blank.
+    fn glyph_row(&self, c: char, y: u8) -> u32;
+}
+
+// Every 8x8 glyph set font8x8 has a use for on a watch, looked up in turn. Anything else is blank.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Font8x8;
+
+impl Font8x8 {
+    fn glyph(c: char) -> Option<[u8; 8]> {
+        BasicFonts::new()
+            .get(c)
+            .or_else(|| LatinFonts::new().get(c))
+            .or_else(|| GreekFonts::new().get(c))
+            .or_else(|| BoxFonts::new().get(c))
+            .or_else(|| BlockFonts::new().get(c))
+            .or_else(|| MiscFonts::new().get(c))
+            .or_else(|| HiraganaFonts::new().get(c))
+    }
+}
+
+impl Font for Font8x8 {
+    fn height(&self) -> u8 {
+        8
+    }
+    fn baseline(&self) -> u8 {
+        // The bottom row is left for descenders
+        7
+    }
+    fn advance(&self, _c: char) -> u8 {
+        8
+    }
+    fn glyph_row(&self, c: char, y: u8) -> u32 {
+        // font8x8 keeps the leftmost pixel in the bottom bit
+        let row = Font8x8::glyph(c).map_or(0, |glyph| glyph[y as usize]);
+        (row.reverse_bits() as u32) << 24
+    }
+}
+
+// Where a glyph's rows are in a BitmapFont. Rows above top and below top + rows are blank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BitmapGlyph {
+    pub c: char,
+    pub advance: u8,
+    pub width: u8,
+    pub top: u8,
+    pub rows: u8,
+    // Where the glyph's first row starts in the font's bitmap. Each row takes (width + 7) / 8 bytes.
+    pub offset: u32,
+}
+
+// A proportional font in plain tables, so it can be a const with nothing to allocate. The bdf module
+// generates these from BDF files.
+#[derive(Clone, Copy, Debug)]
+pub struct BitmapFont<'a> {
+    pub height: u8,
+    pub baseline: u8,
+    // Drawn in place of characters the font doesn't have
+    pub default_char: char,
+    // Sorted by character
+    pub glyphs: &'a [BitmapGlyph],
+    pub bitmap: &'a [u8],
+}
+
+impl BitmapFont<'_> {
+    fn glyph(&self, c: char) -> Option<&BitmapGlyph> {
+        let find = |c: char| {
+            self.glyphs
+                .binary_search_by_key(&c, |glyph| glyph.c)
+                .ok()
+                .map(|index| &self.glyphs[index])
+        };
+        find(c).or_else(|| find(self.default_char))
+    }
+}
+
+impl Font for BitmapFont<'_> {
+    fn height(&self) -> u8 {
+        self.height
+    }
+    fn baseline(&self) -> u8 {
+        self.baseline
+    }
+    fn advance(&self, c: char) -> u8 {
+        self.glyph(c).map_or(0, |glyph| glyph.advance)
+    }
+    fn glyph_width(&self, c: char) -> u8 {
+        self.glyph(c).map_or(0, |glyph| glyph.width)
+    }
+    fn glyph_row(&self, c: char, y: u8) -> u32 {
+        let Some(glyph) = self.glyph(c) else {
+            return 0;
+        };
+        if y < glyph.top || y - glyph.top >= glyph.rows {
+            return 0;
+        }
+        let row_bytes = glyph.width.div_ceil(8) as usize;
+        let start = glyph.offset as usize + (y - glyph.top) as usize * row_bytes;
+        let mut row = 0;
+        for (i, byte) in self.bitmap[start..start + row_bytes]
+            .iter()
+            .enumerate()
+            .take(4)
+        {
+            row |= (*byte as u32) << (24 - i * 8);
+        }
+        row
+    }
+}
@@ -0,0 +1,35 @@
+use crate::TRANSPARENT;
+
+// A 1bpp picture packed like the screen buffer: each row starts on a new byte, the top bit is the leftmost
+// pixel, and 1 is set. The image_file module turns PBM, XBM and PNG files into these.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bitmap<'a> {
+    pub width: u8,
+    pub height: u8,
+    pub data: &'a [u8],
+    // Packed the same way. Pixels left out of the mask are transparent.
+    pub mask: Option<&'a [u8]>,
+}
+
+impl Bitmap<'_> {
+    pub fn row_bytes(&self) -> usize {
+        self.width.div_ceil(8) as usize
+    }
+    // Whether data and mask hold every row
+    pub fn is_complete(&self) -> bool {
+        let len = self.row_bytes() * self.height as usize;
+        self.data.len() >= len && self.mask.is_none_or(|mask| mask.len() >= len)
+    }
+    // 0, 1, or TRANSPARENT outside the image and where it's masked out
+    pub fn get_pixel(&self, x: u8, y: u8) -> u8 {
+        if x >= self.width || y >= self.height {
+            return TRANSPARENT;
+        }
+        let index = y as usize * self.row_bytes() + x as usize / 8;
+        let bit = 0x80 >> (x % 8);
+        if self.mask.is_some_and(|mask| mask[index] & bit == 0) {
+            return TRANSPARENT;
+        }
+        u8::from(self.data[index] & bit != 0)
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{Bitmap, Dither, dither};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+mod png;
+
+// Why an image file couldn't be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageError {
+    pub message: &'static str,
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
+fn error(message: &'static str) -> ImageError {
+    ImageError { message }
+}
+
+// Bitmaps measure their sides in a u8
+fn check_size(width: usize, height: usize) -> Result<(u8, u8), ImageError> {
+    match (u8::try_from(width), u8::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(error("images can be at most 255 pixels across")),
+    }
+}
+
+// A PBM, XBM or PNG file turned into Bitmap data. Use it as it is on the host, or write it out as Rust
+// source for the firmware to build in. Dark pixels are set, and pixels less than half opaque are left out
+// of the mask - there's only a mask if some are. PNGs are thresholded unless they're given another Dither.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageFile {
+    pub width: u8,
+    pub height: u8,
+    pub data: Vec<u8>,
+    pub mask: Option<Vec<u8>>,
+}
+
+impl ImageFile {
+    // Works out the format from how the file starts
+    pub fn parse(bytes: &[u8]) -> Result<ImageFile, ImageError> {
+        ImageFile::parse_dithered(bytes, Dither::Threshold)
+    }
+
+    // Like parse, dithering PNGs with method. The other formats are already 1bpp.
+    pub fn parse_dithered(bytes: &[u8], method: Dither) -> Result<ImageFile, ImageError> {
+        if bytes.starts_with(&png::SIGNATURE) {
+            ImageFile::from_png_dithered(bytes, method)
+        } else if bytes.starts_with(b"P1") || bytes.starts_with(b"P4") {
+            ImageFile::from_pbm(bytes)
+        } else {
+            let source = core::str::from_utf8(bytes).map_err(|_| error("unknown image format"))?;
+            ImageFile::from_xbm(source)
+        }
+    }
+
+    // Plain (P1) or raw (P4) PBM. Raw PBM is already packed like a Bitmap.
+    pub fn from_pbm(bytes: &[u8]) -> Result<ImageFile, ImageError> {
+        let mut pos = 0;
+        let magic = pbm_token(bytes, &mut pos);
+        let raw = match magic {
+            b"P1" => false,
+            b"P4" => true,
+            _ => return Err(error("not a PBM file")),
+        };
+        let mut number = || {
+            core::str::from_utf8(pbm_token(bytes, &mut pos))
+                .ok()
+                .and_then(|token| token.parse::<usize>().ok())
+                .ok_or(error("expected a number in the PBM header"))
+        };
+        let (width, height) = (number()?, number()?);
+        let mut image = ImageFile::blank(width, height)?;
+        let row_bytes = image.bitmap().row_bytes();
+        if raw {
+            // A single whitespace byte separates the header from the data
+            let data = bytes
+                .get(pos + 1..pos + 1 + row_bytes * height)
+                .ok_or(error("PBM data is cut short"))?;
+            image.data.copy_from_slice(data);
+            image.clear_padding();
+        } else {
+            let mut digits = bytes[pos..].iter().filter(|b| !b.is_ascii_whitespace());
+            for y in 0..height {
+                for x in 0..width {
+                    match digits.next() {
+                        Some(b'0') => {}
+                        Some(b'1') => image.data[y * row_bytes + x / 8] |= 0x80 >> (x % 8),
+                        Some(_) => return Err(error("plain PBM pixels should be 0 or 1")),
+                        None => return Err(error("PBM data is cut short")),
+                    }
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    // The C source X bitmaps are saved as. XBM puts the leftmost pixel in the lowest bit, so the bytes get
+    // flipped.
+    pub fn from_xbm(source: &str) -> Result<ImageFile, ImageError> {
+        let define = |suffix: &str| {
+            source
+                .lines()
+                .filter_map(|line| {
+                    let mut words = line.split_whitespace();
+                    (words.next() == Some("#define")).then_some(())?;
+                    let name = words.next()?;
+                    name.ends_with(suffix).then_some(())?;
+                    words.next()?.parse::<usize>().ok()
+                })
+                .next()
+                .ok_or(error("missing XBM width or height"))
+        };
+        let (width, height) = (define("_width")?, define("_height")?);
+        let mut image = ImageFile::blank(width, height)?;
+        let body = source
+            .split_once('{')
+            .and_then(|(_, rest)| rest.split_once('}'))
+            .ok_or(error("missing XBM data"))?
+            .0;
+        let mut bytes = body.split(',').map(str::trim).filter(|s| !s.is_empty());
+        for byte in image.data.iter_mut() {
+            let value = bytes.next().ok_or(error("XBM data is cut short"))?;
+            let value = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+                Some(hex) => u8::from_str_radix(hex, 16),
+                None => value.parse(),
+            };
+            *byte = value
+                .map_err(|_| error("XBM data should be bytes"))?
+                .reverse_bits();
+        }
+        image.clear_padding();
+        Ok(image)
+    }
+
+    // Non-interlaced PNG of any color type and bit depth
+    pub fn from_png(bytes: &[u8]) -> Result<ImageFile, ImageError> {
+        ImageFile::from_png_dithered(bytes, Dither::Threshold)
+    }
+
+    pub fn from_png_dithered(bytes: &[u8], method: Dither) -> Result<ImageFile, ImageError> {
+        let pixels = png::decode(bytes)?;
+        let mut image = ImageFile::blank(pixels.width, pixels.height)?;
+        let luma: Vec<u8> = pixels.luma_alpha.iter().map(|&[luma, _]| luma).collect();
+        dither(&luma, image.width, image.height, method, &mut image.data);
+        let row_bytes = image.bitmap().row_bytes();
+        let mut mask = vec![0; image.data.len()];
+        for (i, &[_, alpha]) in pixels.luma_alpha.iter().enumerate() {
+            let x = i % pixels.width;
+            if alpha >= 0x80 {
+                mask[i / pixels.width * row_bytes + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        if pixels.luma_alpha.iter().any(|&[_, alpha]| alpha < 0x80) {
+            image.mask = Some(mask);
+        }
+        Ok(image)
+    }
+
+    fn blank(width: usize, height: usize) -> Result<ImageFile, ImageError> {
+        let (width, height) = check_size(width, height)?;
+        Ok(ImageFile {
+            width,
+            height,
+            data: vec![0; width.div_ceil(8) as usize * height as usize],
+            mask: None,
+        })
+    }
+
+    // Zeroes the bits past the right edge, which files are free to fill with anything
+    fn clear_padding(&mut self) {
+        let padding = (8 - self.width % 8) % 8;
+        if padding == 0 {
+            return;
+        }
+        let row_bytes = self.bitmap().row_bytes();
+        for row in self.data.chunks_mut(row_bytes) {
+            row[row_bytes - 1] &= 0xFF << padding;
+        }
+    }
+
+    pub fn bitmap(&self) -> Bitmap<'_> {
+        Bitmap {
+            width: self.width,
+            height: self.height,
+            data: &self.data,
+            mask: self.mask.as_deref(),
+        }
+    }
+
+    pub fn to_rust(&self, const_name: &str) -> String {
+        let mut out = String::new();
+        // Writing to a String can't fail
+        let _ = self.write_rust(&mut out, const_name);
+        out
+    }
+
+    fn write_rust(&self, out: &mut String, const_name: &str) -> fmt::Result {
+        writeln!(out, "#[rustfmt::skip]")?;
+        writeln!(
+            out,
+            "pub const {const_name}: watch_lib::Bitmap<'static> = watch_lib::Bitmap {{"
+        )?;
+        writeln!(out, "    width: {},", self.width)?;
+        writeln!(out, "    height: {},", self.height)?;
+        write!(out, "    data: ")?;
+        self.write_rows(out, &self.data)?;
+        writeln!(out, ",")?;
+        match &self.mask {
+            Some(mask) => {
+                write!(out, "    mask: Some(")?;
+                self.write_rows(out, mask)?;
+                writeln!(out, "),")?;
+            }
+            None => writeln!(out, "    mask: None,")?,
+        }
+        writeln!(out, "}};")
+    }
+
+    // A line of binary literals per row, so the picture can be made out in the source
+    fn write_rows(&self, out: &mut String, bytes: &[u8]) -> fmt::Result {
+        writeln!(out, "&[")?;
+        for row in bytes.chunks(self.bitmap().row_bytes().max(1)) {
+            write!(out, "       ")?;
+            for byte in row {
+                write!(out, " 0b{byte:08b},")?;
+            }
+            writeln!(out)?;
+        }
+        write!(out, "    ]")
+    }
+}
+
+// The next whitespace-separated word of a PBM header, skipping # comments
+fn pbm_token<'a>(bytes: &'a [u8], pos: &mut usize) -> &'a [u8] {
+    loop {
+        while bytes.get(*pos).is_some_and(u8::is_ascii_whitespace) {
+            *pos += 1;
+        }
+        if bytes.get(*pos) != Some(&b'#') {
+            break;
+        }
+        while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+            *pos += 1;
+        }
+    }
+    let start = *pos;
+    while bytes
+        .get(*pos)
+        .is_some_and(|&b| !b.is_ascii_whitespace() && b != b'#')
+    {
+        *pos += 1;
+    }
+    &bytes[start..*pos]
+}
@@ -0,0 +1,477 @@
+// Just enough PNG to read icons: every color type and bit depth, but not interlacing
+use super::{ImageError, check_size, error};
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub(super) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+const CUT_SHORT: &str = "PNG data is cut short";
+const TOO_BIG: &str = "PNG data is bigger than its header says";
+
+pub(super) struct Pixels {
+    pub width: usize,
+    pub height: usize,
+    // Row by row, each pixel's brightness and opacity out of 255
+    pub luma_alpha: Vec<[u8; 2]>,
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    depth: u8,
+    color_type: u8,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Header, ImageError> {
+        let data: &[u8; 13] = data
+            .try_into()
+            .map_err(|_| error("IHDR chunk is the wrong size"))?;
+        let [width, height] = [0, 4].map(|at| {
+            u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as usize
+        });
+        let [depth, color_type, compression, filter, interlace] =
+            [8, 9, 10, 11, 12].map(|at| data[at]);
+        check_size(width, height)?;
+        if compression != 0 || filter != 0 {
+            return Err(error("unknown PNG compression or filter method"));
+        }
+        if interlace != 0 {
+            return Err(error("interlaced PNGs aren't supported"));
+        }
+        let depth_ok = match color_type {
+            0 => matches!(depth, 1 | 2 | 4 | 8 | 16),
+            3 => matches!(depth, 1 | 2 | 4 | 8),
+            2 | 4 | 6 => matches!(depth, 8 | 16),
+            _ => return Err(error("unknown PNG color type")),
+        };
+        if !depth_ok {
+            return Err(error("bit depth doesn't suit the color type"));
+        }
+        Ok(Header {
+            width,
+            height,
+            depth,
+            color_type,
+        })
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    // Bytes per row, not counting the filter byte
+    fn stride(&self) -> usize {
+        (self.width * self.channels() * self.depth as usize).div_ceil(8)
+    }
+}
+
+pub(super) fn decode(bytes: &[u8]) -> Result<Pixels, ImageError> {
+    let mut rest = bytes
+        .strip_prefix(&SIGNATURE)
+        .ok_or(error("not a PNG file"))?;
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut transparency = Vec::new();
+    let mut compressed = Vec::new();
+    while let [l0, l1, l2, l3, k0, k1, k2, k3, ..] = *rest {
+        let len = u32::from_be_bytes([l0, l1, l2, l3]) as usize;
+        // The length, kind, data and CRC
+        let end = len.checked_add(12).ok_or(error(CUT_SHORT))?;
+        let chunk = rest.get(..end).ok_or(error(CUT_SHORT))?;
+        let (checked, crc) = chunk[4..].split_at(len + 4);
+        if crc32(checked).to_be_bytes() != crc {
+            return Err(error("PNG chunk fails its CRC"));
+        }
+        let data = &checked[4..];
+        rest = &rest[end..];
+        match &[k0, k1, k2, k3] {
+            b"IHDR" => header = Some(Header::parse(data)?),
+            b"PLTE" => palette = data.chunks_exact(3).collect(),
+            b"tRNS" => transparency = data.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+    let header = header.ok_or(error("missing IHDR chunk"))?;
+    // Each row starts with its filter byte
+    let filtered_len = (header.stride() + 1) * header.height;
+    let rows = unfilter(&header, &inflate(&compressed, filtered_len)?)?;
+
+    let stride = header.stride();
+    let depth = header.depth as usize;
+    let mut luma_alpha = Vec::with_capacity(header.width * header.height);
+    for row in rows.chunks_exact(stride.max(1)).take(header.height) {
+        let raw = |i: usize| sample(row, i, depth);
+        // Scaled to 0..=255
+        let value = |i: usize| match depth {
+            16 => raw(i) >> 8,
+            8 => raw(i),
+            _ => raw(i) * 255 / ((1 << depth) - 1),
+        } as u8;
+        for x in 0..header.width {
+            let pixel = match header.color_type {
+                0 => {
+                    let see_through = transparency.len() >= 2
+                        && raw(x)
+                            == u16::from_be_bytes([transparency[0], transparency[1]]) as usize;
+                    [value(x), if see_through { 0 } else { 255 }]
+                }
+                2 => {
+                    let see_through = transparency.len() >= 6
+                        && (0..3).all(|c| {
+                            raw(x * 3 + c)
+                                == u16::from_be_bytes([
+                                    transparency[c * 2],
+                                    transparency[c * 2 + 1],
+                                ]) as usize
+                        });
+                    let luma = luma(value(x * 3), value(x * 3 + 1), value(x * 3 + 2));
+                    [luma, if see_through { 0 } else { 255 }]
+                }
+                3 => {
+                    let index = raw(x);
+                    let color = palette
+                        .get(index)
+                        .ok_or(error("palette index out of range"))?;
+                    let alpha = transparency.get(index).copied().unwrap_or(255);
+                    [luma(color[0], color[1], color[2]), alpha]
+                }
+                4 => [value(x * 2), value(x * 2 + 1)],
+                _ => [
+                    luma(value(x * 4), value(x * 4 + 1), value(x * 4 + 2)),
+                    value(x * 4 + 3),
+                ],
+            };
+            luma_alpha.push(pixel);
+        }
+    }
+    Ok(Pixels {
+        width: header.width,
+        height: header.height,
+        luma_alpha,
+    })
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+// The index-th sample of a row, as stored
+fn sample(row: &[u8], index: usize, depth: usize) -> usize {
+    match depth {
+        16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]) as usize,
+        8 => row[index] as usize,
+        _ => {
+            let bit = index * depth;
+            (row[bit / 8] as usize >> (8 - depth - bit % 8)) & ((1 << depth) - 1)
+        }
+    }
+}
+
+// Undoes the per-row prediction PNG compresses with, leaving the rows packed back to back
+fn unfilter(header: &Header, filtered: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let stride = header.stride();
+    if filtered.len() < (stride + 1) * header.height {
+        return Err(error(CUT_SHORT));
+    }
+    // Filters look back a whole pixel, or a byte for pixels smaller than one
+    let back = (header.channels() * header.depth as usize).div_ceil(8);
+    let mut rows = vec![0u8; stride * header.height];
+    for y in 0..header.height {
+        let line = &filtered[y * (stride + 1)..][..stride + 1];
+        let (above, current) = rows.split_at_mut(y * stride);
+        let above = above
+            .get(above.len().saturating_sub(stride)..)
+            .unwrap_or(&[]);
+        let current = &mut current[..stride];
+        for i in 0..stride {
+            let left = if i >= back { current[i - back] } else { 0 };
+            let up = above.get(i).copied().unwrap_or(0);
+            let up_left = if i >= back {
+                above.get(i - back).copied().unwrap_or(0)
+            } else {
+                0
+            };
+            let predicted = match line[0] {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(error("unknown PNG row filter")),
+            };
+            current[i] = line[i + 1].wrapping_add(predicted);
+        }
+    }
+    Ok(rows)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let left_distance = (estimate - left as i16).abs();
+    let up_distance = (estimate - up as i16).abs();
+    let up_left_distance = (estimate - up_left as i16).abs();
+    if left_distance <= up_distance && left_distance <= up_left_distance {
+        left
+    } else if up_distance <= up_left_distance {
+        up
+    } else {
+        up_left
+    }
+}
+
+// Reads bits least significant first, the way DEFLATE packs them
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn take(&mut self, n: u32) -> Result<usize, ImageError> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or(error(CUT_SHORT))?;
+            self.pos += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value as usize)
+    }
+
+    // Skips to the next whole byte. There's never a whole unread byte in the buffer.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+// A canonical Huffman code, as the number of codes of each length and the symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<usize, ImageError> {
+        // Codes of each length follow on from the shorter ones, read a bit at a time
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= bits.take(1)?;
+            let count = count as usize;
+            if code < first + count {
+                return Ok(self.symbols[index + code - first] as usize);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(error("bad Huffman code in PNG data"))
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// The order dynamic blocks list the code length code's lengths in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+// Unpacks zlib-wrapped DEFLATE data, giving up if it comes to more than limit bytes
+fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageError> {
+    let [method, flags, ..] = *data else {
+        return Err(error(CUT_SHORT));
+    };
+    if method & 0x0F != 8 {
+        return Err(error("unknown zlib compression method"));
+    }
+    if flags & 0x20 != 0 {
+        return Err(error("zlib preset dictionaries aren't supported"));
+    }
+    let mut bits = Bits {
+        data,
+        pos: 2,
+        buffer: 0,
+        count: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => {
+                bits.align();
+                let header = data.get(bits.pos..bits.pos + 4).ok_or(error(CUT_SHORT))?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let stored = data
+                    .get(bits.pos + 4..bits.pos + 4 + len)
+                    .ok_or(error(CUT_SHORT))?;
+                if out.len() + len > limit {
+                    return Err(error(TOO_BIG));
+                }
+                out.extend_from_slice(stored);
+                bits.pos += 4 + len;
+            }
+            1 => {
+                let mut lengths = [0; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut bits, &mut out, limit, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut out, limit, &literals, &distances)?;
+            }
+            _ => return Err(error("bad DEFLATE block type")),
+        }
+        if last {
+            bits.align();
+            let checksum = data.get(bits.pos..bits.pos + 4).ok_or(error(CUT_SHORT))?;
+            if adler32(&out).to_be_bytes() != checksum {
+                return Err(error("PNG data fails its checksum"));
+            }
+            return Ok(out);
+        }
+    }
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = bits.take(5)? + 257;
+    let distance_count = bits.take(5)? + 1;
+    let code_length_count = bits.take(4)? + 4;
+    let mut code_lengths = [0; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = bits.take(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let total = literal_count + distance_count;
+    let mut lengths = Vec::with_capacity(total);
+    while lengths.len() < total {
+        let (len, repeat) = match code_lengths.decode(bits)? {
+            len @ 0..=15 => (len as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or(error("bad DEFLATE code lengths"))?;
+                (previous, 3 + bits.take(2)?)
+            }
+            17 => (0, 3 + bits.take(3)?),
+            _ => (0, 11 + bits.take(7)?),
+        };
+        if lengths.len() + repeat > total {
+            return Err(error("bad DEFLATE code lengths"));
+        }
+        lengths.extend(core::iter::repeat_n(len, repeat));
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    limit: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(bits)?;
+        if symbol < 256 {
+            if out.len() == limit {
+                return Err(error(TOO_BIG));
+            }
+            out.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+        let i = symbol - 257;
+        if i >= LENGTH_BASE.len() {
+            return Err(error("bad DEFLATE length"));
+        }
+        let len = LENGTH_BASE[i] as usize + bits.take(LENGTH_EXTRA[i] as u32)?;
+        let d = distances.decode(bits)?;
+        if d >= DISTANCE_BASE.len() {
+            return Err(error("bad DEFLATE distance"));
+        }
+        let distance = DISTANCE_BASE[d] as usize + bits.take(DISTANCE_EXTRA[d] as u32)?;
+        if distance > out.len() {
+            return Err(error("DEFLATE distance reaches back past the start"));
+        }
+        if out.len() + len > limit {
+            return Err(error(TOO_BIG));
+        }
+        // The copy can overlap what it's writing, repeating a short run
+        for _ in 0..len {
+            out.push(out[out.len() - distance]);
+        }
+    }
+}
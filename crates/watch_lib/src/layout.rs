@@ -0,0 +1,222 @@
+use crate::{BoundingRect, ElementId};
+
+// Which way a container lines up its children
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    // Left to right
+    Row,
+    // Top to bottom
+    Column,
+    // On top of each other, each aligned within the container on its own
+    Stack,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+// Arranges a container's visible children inside it. Hidden children take up no space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub direction: Direction,
+    // Space between the container's edges and its children
+    pub padding: u8,
+    // Space between neighbouring children in a row or column
+    pub spacing: u8,
+    // Where children sit across the container when they don't fill it. In a row this places the whole run
+    // of children, and in a column each child on its own.
+    pub horizontal: Align,
+    // Likewise down the container
+    pub vertical: Align,
+}
+
+impl Layout {
+    pub fn row() -> Layout {
+        Layout::new(Direction::Row)
+    }
+    pub fn column() -> Layout {
+        Layout::new(Direction::Column)
+    }
+    pub fn stack() -> Layout {
+        Layout::new(Direction::Stack)
+    }
+    fn new(direction: Direction) -> Layout {
+        Layout {
+            direction,
+            padding: 0,
+            spacing: 0,
+            horizontal: Align::Start,
+            vertical: Align::Start,
+        }
+    }
+}
+
+// How big an element wants to be along one axis when a container lays it out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Size {
+    // Its intrinsic size, or its current size if it doesn't have one
+    #[default]
+    Fit,
+    Fixed(u8),
+    // An even share of whatever the container has left over, or all of it across a row or column
+    Fill,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sizing {
+    pub width: Size,
+    pub height: Size,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct LayoutItem {
+    pub(crate) id: ElementId,
+    pub(crate) sizing: Sizing,
+    // The element's intrinsic size if it has one, otherwise its current rect's
+    pub(crate) natural_width: u8,
+    pub(crate) natural_height: u8,
+    // Filled in by arrange, relative to the container
+    pub(crate) rect: BoundingRect,
+}
+
+// Splits items into the axis children are lined up along and the one across it
+fn main_axis(layout: &Layout, width: i16, height: i16) -> (i16, i16) {
+    match layout.direction {
+        Direction::Column => (height, width),
+        _ => (width, height),
+    }
+}
+
+fn item_axes(layout: &Layout, item: &LayoutItem) -> ((Size, i16), (Size, i16)) {
+    let horizontal = (item.sizing.width, item.natural_width as i16);
+    let vertical = (item.sizing.height, item.natural_height as i16);
+    match layout.direction {
+        Direction::Column => (vertical, horizontal),
+        _ => (horizontal, vertical),
+    }
+}
+
+fn resolve(size: Size, natural: i16, available: i16) -> i16 {
+    match size {
+        Size::Fit => natural,
+        Size::Fixed(fixed) => fixed as i16,
+        Size::Fill => available.max(0),
+    }
+}
+
+pub(crate) fn align_offset(align: Align, available: i16, used: i16) -> i16 {
+    match align {
+        Align::Start => 0,
+        Align::Center => (available - used) / 2,
+        Align::End => available - used,
+    }
+}
+
+fn clamp_size(size: i16) -> u8 {
+    size.clamp(0, u8::MAX as i16) as u8
+}
+
+// Smallest size that fits the items without squashing them - fill items count as their natural size
+pub(crate) fn measure(layout: &Layout, items: impl Iterator<Item = LayoutItem>) -> (u8, u8) {
+    let (mut main, mut cross, mut count) = (0i16, 0i16, 0i16);
+    for item in items {
+        let ((main_size, main_natural), (cross_size, cross_natural)) = item_axes(layout, &item);
+        let item_main = resolve(main_size, main_natural, main_natural);
+        let item_cross = resolve(cross_size, cross_natural, cross_natural);
+        if layout.direction == Direction::Stack {
+            main = main.max(item_main);
+        } else {
+            main = main.saturating_add(item_main);
+        }
+        cross = cross.max(item_cross);
+        count += 1;
+    }
+    if layout.direction != Direction::Stack && count > 1 {
+        main = main.saturating_add((count - 1) * layout.spacing as i16);
+    }
+    let padding = 2 * layout.padding as i16;
+    let (main, cross) = (main.saturating_add(padding), cross.saturating_add(padding));
+    match layout.direction {
+        Direction::Column => (clamp_size(cross), clamp_size(main)),
+        _ => (clamp_size(main), clamp_size(cross)),
+    }
+}
+
+// Works out each item's rect within a container of the given size
+pub(crate) fn arrange(layout: &Layout, width: u8, height: u8, items: &mut [LayoutItem]) {
+    let padding = layout.padding as i16;
+    let inner_width = width as i16 - 2 * padding;
+    let inner_height = height as i16 - 2 * padding;
+
+    if layout.direction == Direction::Stack {
+        for item in items.iter_mut() {
+            let item_width = resolve(item.sizing.width, item.natural_width as i16, inner_width);
+            let item_height = resolve(item.sizing.height, item.natural_height as i16, inner_height);
+            item.rect = BoundingRect {
+                x: padding + align_offset(layout.horizontal, inner_width, item_width),
+                y: padding + align_offset(layout.vertical, inner_height, item_height),
+                width: clamp_size(item_width),
+                height: clamp_size(item_height),
+            };
+        }
+        return;
+    }
+
+    let (main_available, cross_available) = main_axis(layout, inner_width, inner_height);
+    let (main_align, cross_align) = match layout.direction {
+        Direction::Column => (layout.vertical, layout.horizontal),
+        _ => (layout.horizontal, layout.vertical),
+    };
+    let gaps = items.len().saturating_sub(1) as i16 * layout.spacing as i16;
+    let mut fixed_main = gaps;
+    let mut fill_count = 0;
+    for item in items.iter() {
+        let ((main_size, main_natural), _) = item_axes(layout, item);
+        if main_size == Size::Fill {
+            fill_count += 1;
+        } else {
+            fixed_main += resolve(main_size, main_natural, 0);
+        }
+    }
+    let left_over = (main_available - fixed_main).max(0);
+    let used = if fill_count > 0 {
+        fixed_main + left_over
+    } else {
+        fixed_main
+    };
+
+    let mut position = padding + align_offset(main_align, main_available, used);
+    let mut fills_placed = 0;
+    for item in items.iter_mut() {
+        let ((main_size, main_natural), (cross_size, cross_natural)) = item_axes(layout, item);
+        let item_main = if main_size == Size::Fill {
+            // The first few fill items take the remainder a pixel each
+            let share = left_over / fill_count + i16::from(fills_placed < left_over % fill_count);
+            fills_placed += 1;
+            share
+        } else {
+            resolve(main_size, main_natural, 0)
+        };
+        let item_cross = resolve(cross_size, cross_natural, cross_available);
+        let cross_position = padding + align_offset(cross_align, cross_available, item_cross);
+        item.rect = match layout.direction {
+            Direction::Column => BoundingRect {
+                x: cross_position,
+                y: position,
+                width: clamp_size(item_cross),
+                height: clamp_size(item_main),
+            },
+            _ => BoundingRect {
+                x: position,
+                y: cross_position,
+                width: clamp_size(item_main),
+                height: clamp_size(item_cross),
+            },
+        };
+        position += item_main + layout.spacing as i16;
+    }
+}
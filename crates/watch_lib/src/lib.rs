@@ -1,132 +1,945 @@
-// #![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 extern crate alloc;
 use alloc::boxed::Box;
 use alloc::rc::Rc;
 use alloc::string::String;
 use alloc::vec::Vec;
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell, RefMut};
 use core::cmp::{max, min};
-use font8x8::UnicodeFonts;
-use hashbrown::HashSet;
+use core::ops::Range;
 
 // TODO: maybe signals becomes a split module
 pub mod signals;
 pub use signals::*;
+pub mod display;
+pub use display::*;
+pub mod fixed;
+pub use fixed::*;
+pub mod layout;
+pub use layout::*;
+use layout::{LayoutItem, align_offset, arrange, measure};
+pub mod font;
+pub use font::*;
+// Host-side converters, which the firmware has no use for
+#[cfg(feature = "std")]
+pub mod bdf;
+pub mod text;
+pub use text::*;
+pub mod image;
+pub use image::*;
+pub mod dither;
+pub use dither::*;
+pub mod shape;
+use shape::ShapeBits;
+pub use shape::*;
+// Host-side like bdf
+#[cfg(feature = "std")]
+pub mod image_file;
+use text::{ELLIPSIS, Lines, advance, line_height, or_byte, shown_as, stretched_byte};
 
 pub const SCREEN_WIDTH: u8 = 200;
 pub const SCREEN_HEIGHT: u8 = 200;
+// Rows start on a byte boundary since the width is a multiple of 8
+pub const ROW_BYTES: usize = SCREEN_WIDTH as usize / 8;
+const FULL_SCREEN: BoundingRect = BoundingRect {
+    x: 0,
+    y: 0,
+    width: SCREEN_WIDTH,
+    height: SCREEN_HEIGHT,
+};
+// Clip for elements that can't show at all
+const NOWHERE: BoundingRect = BoundingRect {
+    x: 0,
+    y: 0,
+    width: 0,
+    height: 0,
+};
 
-struct ArbitraryIdStore<V> {
-    data: Vec<Option<V>>,
+// A color that leaves whatever is underneath showing, for elements that aren't solid rectangles
+pub const TRANSPARENT: u8 = u8::MAX;
+
+// In fixed-capacity builds these are hard limits, otherwise they're just how much room is reserved up front
+pub const MAX_ELEMENTS: usize = 64;
+const REGION_CAPACITY: usize = 64;
+const EDGE_CAPACITY: usize = 2 * REGION_CAPACITY;
+const SCREEN_BUFFER_LEN: usize = ROW_BYTES * SCREEN_HEIGHT as usize;
+
+// A slot index plus the generation of the value it was handed out for. Freeing a slot bumps its
+// generation, so keys to the old value stop resolving rather than reaching whatever reuses the slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct SlotKey {
+    index: usize,
+    generation: u32,
+}
+
+struct Slot<V> {
+    generation: u32,
+    value: Option<V>,
 }
 
-impl<V> ArbitraryIdStore<V> {
-    fn add(&mut self, value: V) -> usize {
-        // TODO: is there a more performant way to fill old gaps? remember, index matters - maybe this should be a hashmap instead
+pub(crate) struct ArbitraryIdStore<V, const N: usize> {
+    data: CapacityVec<Slot<V>, N>,
+}
+
+impl<V, const N: usize> ArbitraryIdStore<V, N> {
+    fn new() -> Self {
+        ArbitraryIdStore {
+            data: CapacityVec::new(),
+        }
+    }
+    fn add(&mut self, value: V) -> Result<SlotKey, CapacityError> {
+        // TODO: is there a more performant way to fill old gaps?
         let chosen_index = self
             .data
             .iter()
             .enumerate()
             .rev()
-            .find(|(_, v)| v.is_none())
+            .find(|(_, slot)| slot.value.is_none())
             .map(|i| i.0);
-        if let Some(i) = chosen_index {
-            self.data[i] = Some(value);
-            return i;
+        if let Some(index) = chosen_index {
+            let slot = &mut self.data[index];
+            slot.value = Some(value);
+            Ok(SlotKey {
+                index,
+                generation: slot.generation,
+            })
         } else {
-            self.data.push(Some(value));
-            return self.data.len() - 1;
+            self.data.try_push(Slot {
+                generation: 0,
+                value: Some(value),
+            })?;
+            Ok(SlotKey {
+                index: self.data.len() - 1,
+                generation: 0,
+            })
         }
     }
-    fn delete(&mut self, id: usize) {
-        self.data[id] = None;
+    // Returns None without touching anything if the key is stale
+    fn delete(&mut self, key: SlotKey) -> Option<V> {
+        let slot = self.data.get_mut(key.index)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        Some(value)
     }
-    fn get(&self, id: usize) -> Option<&V> {
-        if self.data.get(id).unwrap_or(&None).is_none() {
-            None
-        } else {
-            Some(&self.data[id].as_ref().unwrap())
+    fn get(&self, key: SlotKey) -> Option<&V> {
+        self.data
+            .get(key.index)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+    fn get_mut(&mut self, key: SlotKey) -> Option<&mut V> {
+        self.data
+            .get_mut(key.index)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+    fn values(&self) -> impl Iterator<Item = &V> {
+        self.data.iter().filter_map(|slot| slot.value.as_ref())
+    }
+}
+
+// Handle to a mounted element. Handles to unmounted elements are rejected, even once their slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ElementId(SlotKey);
+
+impl ElementId {
+    // The full-screen element every tree hangs off - it's never unmounted
+    pub const ROOT: ElementId = ElementId(SlotKey {
+        index: 0,
+        generation: 0,
+    });
+    // Slot in the element store - distinct among live elements, but reused after an unmount
+    pub fn index(&self) -> usize {
+        self.0.index
+    }
+}
+
+// A set of elements, one bit per slot in the element store
+pub struct ElementSet {
+    words: CapacityVec<u32, { MAX_ELEMENTS.div_ceil(32) }>,
+    len: usize,
+}
+
+impl ElementSet {
+    fn new() -> ElementSet {
+        ElementSet {
+            words: CapacityVec::new(),
+            len: 0,
         }
     }
-    fn get_mut(&mut self, id: usize) -> Option<&mut V> {
-        if self.data.get(id).unwrap_or(&None).is_none() {
-            None
-        } else {
-            Some(self.data[id].as_mut().unwrap())
+    // Returns whether the id wasn't already in the set
+    pub fn insert(&mut self, id: ElementId) -> bool {
+        let (word, bit) = (id.index() / 32, 1 << (id.index() % 32));
+        while self.words.len() <= word {
+            // Element ids never reach past MAX_ELEMENTS in fixed-capacity builds, so this always fits
+            self.words.push(0);
+        }
+        if self.words[word] & bit != 0 {
+            return false;
         }
+        self.words[word] |= bit;
+        self.len += 1;
+        true
+    }
+    pub fn remove(&mut self, id: &ElementId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.words[id.index() / 32] &= !(1 << (id.index() % 32));
+        self.len -= 1;
+        true
+    }
+    pub fn contains(&self, id: &ElementId) -> bool {
+        self.words
+            .get(id.index() / 32)
+            .is_some_and(|word| word & (1 << (id.index() % 32)) != 0)
     }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+        self.len = 0;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ElementTreeNode {
+    element_id: ElementId,
+    global_x: i16,
+    global_y: i16,
+    // Screen area the element may paint in, narrowed by every clipping ancestor
+    clip: BoundingRect,
+}
+
+struct ElementStackEntry {
+    element_id: ElementId,
+    parent_global_x: i16,
+    parent_global_y: i16,
+    clip: BoundingRect,
+}
+
+struct ElementLocation {
+    parent_id: ElementId,
+    // None if the element is its parent's first child
+    previous_sibling_id: Option<ElementId>,
+    parent_global_x: i16,
+    parent_global_y: i16,
+}
+
+struct VisibilityBinding {
+    element_id: ElementId,
+    unsubscribe: Box<dyn Fn()>,
+}
+
+#[derive(Clone, Copy)]
+enum Placement {
+    Before(ElementId),
+    After(ElementId),
+    FirstChildOf(ElementId),
+    LastChildOf(ElementId),
 }
 
 pub struct UIContext {
-    elements: ArbitraryIdStore<Box<dyn UIElement>>,
-    pub elements_requesting_redraw: Rc<RefCell<HashSet<usize>>>,
-    font: font8x8::unicode::BasicFonts,
-    screen_buffer: Vec<u8>,
+    // Elements differ in size, so mounting still boxes one in fixed-capacity builds. Only the slots are fixed.
+    elements: ArbitraryIdStore<Box<dyn UIElement>, MAX_ELEMENTS>,
+    pub elements_requesting_redraw: Rc<RefCell<ElementSet>>,
+    // Hidden elements, which aren't drawn along with everything under them
+    hidden_elements: ElementSet,
+    visibility_bindings: CapacityVec<VisibilityBinding, MAX_ELEMENTS>,
+    // Visibility changes from bound observables, applied on the next handle_draw_requests
+    pending_visibility: Rc<RefCell<CapacityVec<(ElementId, bool), MAX_ELEMENTS>>>,
+    // Screen areas to repaint that no longer belong to an element, like where one was unmounted
+    invalidated_rects: CapacityVec<BoundingRect, REGION_CAPACITY>,
+    // Used by text elements that don't have a font of their own
+    font: &'static dyn Font,
+    screen_buffer: CapacityVec<u8, SCREEN_BUFFER_LEN>,
     // Scratch buffers to avoid per-frame allocations
-    scratch_redraw_sources: Vec<BoundingRect>,
-    scratch_optimized_regions: Vec<BoundingRect>,
-    scratch_sweep_normalized: Vec<BoundingRect>,
-    scratch_sweep_x_edges: Vec<i16>,
-    scratch_sweep_y_spans: Vec<(i16, i16)>,
-    scratch_region_intersections: Vec<BoundingRect>,
+    scratch_ordered_elements: CapacityVec<ElementTreeNode, MAX_ELEMENTS>,
+    scratch_dfs_stack: CapacityVec<ElementStackEntry, MAX_ELEMENTS>,
+    scratch_redraw_sources: CapacityVec<BoundingRect, { MAX_ELEMENTS + REGION_CAPACITY }>,
+    scratch_optimized_regions: CapacityVec<BoundingRect, REGION_CAPACITY>,
+    scratch_sweep_normalized: CapacityVec<BoundingRect, REGION_CAPACITY>,
+    scratch_sweep_x_edges: CapacityVec<i16, EDGE_CAPACITY>,
+    scratch_sweep_y_spans: CapacityVec<(i16, i16), REGION_CAPACITY>,
+    scratch_region_intersections: CapacityVec<BoundingRect, REGION_CAPACITY>,
+    scratch_aligned_sources: CapacityVec<BoundingRect, REGION_CAPACITY>,
+    scratch_aligned_regions: CapacityVec<BoundingRect, REGION_CAPACITY>,
+    scratch_layout_containers: CapacityVec<ElementId, MAX_ELEMENTS>,
+    scratch_fitted_elements: CapacityVec<ElementId, MAX_ELEMENTS>,
+    scratch_layout_items: CapacityVec<LayoutItem, MAX_ELEMENTS>,
+    window_limits: WindowLimits,
 }
 
 impl UIContext {
-    pub fn new(font: font8x8::unicode::BasicFonts) -> UIContext {
+    pub fn new(font: &'static dyn Font) -> UIContext {
+        let mut elements = ArbitraryIdStore::new();
+        // Only there to hang elements off - the screen is cleared before anything paints
+        let root: Box<dyn UIElement> = Box::new(RectUIElement::new(FULL_SCREEN, TRANSPARENT));
+        elements.add(root).unwrap();
+        let mut screen_buffer = CapacityVec::new();
+        for _ in 0..SCREEN_BUFFER_LEN {
+            screen_buffer.push(0);
+        }
         UIContext {
-            elements: ArbitraryIdStore {
-                data: {
-                    let mut vec: Vec<Option<Box<dyn UIElement>>> = Vec::with_capacity(64);
-                    let el = RectUIElement::new(
-                        BoundingRect {
-                            x: 0,
-                            y: 0,
-                            width: SCREEN_WIDTH,
-                            height: SCREEN_HEIGHT,
-                        },
-                        // TODO: make invisible
-                        0,
-                    );
-                    vec.push(Some(Box::new(el)));
-                    vec
-                },
-            },
-            elements_requesting_redraw: Rc::new(RefCell::new(HashSet::with_capacity(64))),
+            elements,
+            elements_requesting_redraw: Rc::new(RefCell::new(ElementSet::new())),
+            hidden_elements: ElementSet::new(),
+            visibility_bindings: CapacityVec::new(),
+            pending_visibility: Rc::new(RefCell::new(CapacityVec::new())),
+            invalidated_rects: CapacityVec::new(),
             font,
-            screen_buffer: alloc::vec![0 as u8; (SCREEN_WIDTH as usize * SCREEN_HEIGHT as usize) / 8],
-            scratch_redraw_sources: Vec::with_capacity(64),
-            scratch_optimized_regions: Vec::with_capacity(64),
-            scratch_sweep_normalized: Vec::with_capacity(64),
-            scratch_sweep_x_edges: Vec::with_capacity(128),
-            scratch_sweep_y_spans: Vec::with_capacity(128),
-            scratch_region_intersections: Vec::with_capacity(64),
+            screen_buffer,
+            scratch_ordered_elements: CapacityVec::new(),
+            scratch_dfs_stack: CapacityVec::new(),
+            scratch_redraw_sources: CapacityVec::new(),
+            scratch_optimized_regions: CapacityVec::new(),
+            scratch_sweep_normalized: CapacityVec::new(),
+            scratch_sweep_x_edges: CapacityVec::new(),
+            scratch_sweep_y_spans: CapacityVec::new(),
+            scratch_region_intersections: CapacityVec::new(),
+            scratch_aligned_sources: CapacityVec::new(),
+            scratch_aligned_regions: CapacityVec::new(),
+            scratch_layout_containers: CapacityVec::new(),
+            scratch_fitted_elements: CapacityVec::new(),
+            scratch_layout_items: CapacityVec::new(),
+            window_limits: WindowLimits::default(),
         }
     }
-    pub fn add_to_root(&mut self, element_id: usize) {
-        let root_ptr: *mut dyn UIElement = &mut **(self.elements.get_mut(0).unwrap());
-        unsafe {
-            (*root_ptr).insert_child_at_end(self, element_id);
-        }
+    // Moves a mounted element to the end of the root's children. Returns false if it isn't in the tree.
+    pub fn add_to_root(&mut self, element_id: ElementId) -> bool {
+        self.reparent(element_id, ElementId::ROOT)
     }
-    pub fn mount<El: UIElement + 'static>(&mut self, parent_id: usize, el: El) -> usize {
-        let parent_ptr: *mut dyn UIElement = &mut **(self.elements.get_mut(parent_id).unwrap());
+    // Panics if the context, or a signal the element listens to, is full in a fixed-capacity build, or if the
+    // parent has been unmounted
+    pub fn mount<El: UIElement + 'static>(&mut self, parent_id: ElementId, el: El) -> ElementId {
+        self.try_mount(parent_id, el)
+            .expect("no room left for another element")
+    }
+    // Like mount, but running out of room is an error and leaves the tree as it was
+    pub fn try_mount<El: UIElement + 'static>(
+        &mut self,
+        parent_id: ElementId,
+        el: El,
+    ) -> Result<ElementId, CapacityError> {
+        let parent_ptr: *mut dyn UIElement = &mut **(self
+            .elements
+            .get_mut(parent_id.0)
+            .expect("parent element isn't mounted"));
         unsafe {
-            let el_id = self.mount_internal(el);
+            let el_id = self.mount_internal(el)?;
             (*parent_ptr).insert_child_at_end(self, el_id);
-            el_id
+            Ok(el_id)
         }
     }
-    fn mount_internal<El: UIElement + 'static>(&mut self, element: El) -> usize {
-        let id = self.elements.add(Box::new(element));
+    fn mount_internal<El: UIElement + 'static>(
+        &mut self,
+        element: El,
+    ) -> Result<ElementId, CapacityError> {
+        let id = ElementId(self.elements.add(Box::new(element))?);
+        let el = self.elements.get(id.0).unwrap();
+        if let Err(error) = el.mount_to_context(self, id) {
+            self.elements.delete(id.0);
+            return Err(error);
+        }
         self.elements_requesting_redraw.borrow_mut().insert(id);
-        let el = self.elements.get(id).unwrap();
-        el.mount_to_context(self, id);
-        id
+        Ok(id)
     }
-    pub fn get_screen_buffer(&self) -> &Vec<u8> {
+    // Removes the element and everything under it, and repaints the area they covered.
+    // Returns false if the id isn't in the tree, including when it was already unmounted or is the root.
+    pub fn unmount(&mut self, id: ElementId) -> bool {
+        let Some(location) = self.locate(id) else {
+            return false;
+        };
+
+        let next_id = self.elements.get(id.0).unwrap().get_next_element_id();
+        if let Some(previous_sibling_id) = location.previous_sibling_id {
+            let previous = self.elements.get_mut(previous_sibling_id.0).unwrap();
+            previous.set_next_element_id(next_id);
+        } else {
+            let parent = self.elements.get_mut(location.parent_id.0).unwrap();
+            parent.set_first_child_id(next_id);
+        }
+
+        self.scratch_dfs_stack.clear();
+        self.scratch_dfs_stack.push(ElementStackEntry {
+            element_id: id,
+            parent_global_x: location.parent_global_x,
+            parent_global_y: location.parent_global_y,
+            clip: FULL_SCREEN,
+        });
+        while let Some(entry) = self.scratch_dfs_stack.pop() {
+            let el = self.elements.get(entry.element_id.0).unwrap();
+            let mut rect = el.get_bounding_rect();
+            rect.x += entry.parent_global_x;
+            rect.y += entry.parent_global_y;
+            // The first element's siblings stay mounted
+            let mut child_id = el.get_first_child_id();
+            while let Some(id) = child_id {
+                self.scratch_dfs_stack.push(ElementStackEntry {
+                    element_id: id,
+                    parent_global_x: rect.x,
+                    parent_global_y: rect.y,
+                    clip: FULL_SCREEN,
+                });
+                child_id = self.elements.get(id.0).unwrap().get_next_element_id();
+            }
+            el.unmount_from_context(self);
+            self.elements.delete(entry.element_id.0);
+            self.elements_requesting_redraw
+                .borrow_mut()
+                .remove(&entry.element_id);
+            self.hidden_elements.remove(&entry.element_id);
+            self.unbind_visibility(entry.element_id);
+            self.invalidate_rect(rect);
+        }
+        true
+    }
+    // Moves a mounted element (with its subtree) to just before sibling, under sibling's parent.
+    // Later siblings paint over earlier ones with the same z-index. Returns false if either isn't in the tree, or if sibling is
+    // inside the element's own subtree.
+    pub fn insert_before(&mut self, id: ElementId, sibling: ElementId) -> bool {
+        self.relink(id, Placement::Before(sibling))
+    }
+    pub fn insert_after(&mut self, id: ElementId, sibling: ElementId) -> bool {
+        self.relink(id, Placement::After(sibling))
+    }
+    // Moves the element to the end of new_parent's children, keeping its position relative to the parent.
+    // Returns false if either isn't in the tree, or if new_parent can't have children.
+    pub fn reparent(&mut self, id: ElementId, new_parent: ElementId) -> bool {
+        self.relink(id, Placement::LastChildOf(new_parent))
+    }
+    // Moves the element to the end of its siblings, so it paints over those with the same z-index
+    pub fn bring_to_front(&mut self, id: ElementId) -> bool {
+        match self.locate(id) {
+            Some(location) => self.relink(id, Placement::LastChildOf(location.parent_id)),
+            None => false,
+        }
+    }
+    // Moves the element to the start of its siblings, so those with the same z-index paint over it
+    pub fn send_to_back(&mut self, id: ElementId) -> bool {
+        match self.locate(id) {
+            Some(location) => self.relink(id, Placement::FirstChildOf(location.parent_id)),
+            None => false,
+        }
+    }
+    // Setters like this one return false, changing nothing, if the element isn't in the tree or doesn't have
+    // the property, like set_color on text. The root doesn't count as in the tree, so they're always false
+    // for it.
+    pub fn set_z_index(&mut self, id: ElementId, z_index: i8) -> bool {
+        self.update_element(id, |el| el.set_z_index(z_index))
+    }
+    // Returns false like set_z_index
+    pub fn set_composite_mode(&mut self, id: ElementId, mode: CompositeMode) -> bool {
+        self.update_element(id, |el| el.set_composite_mode(mode))
+    }
+    // Returns false like set_z_index
+    pub fn set_clips_children(&mut self, id: ElementId, clips_children: bool) -> bool {
+        self.update_element(id, |el| el.set_clips_children(clips_children))
+    }
+    // Moves and resizes the element. Its subtree moves with it. Returns false like set_z_index.
+    pub fn set_rect(&mut self, id: ElementId, rect: BoundingRect) -> bool {
+        self.update_element(id, |el| el.set_bounding_rect(rect))
+    }
+    pub fn set_position(&mut self, id: ElementId, x: i16, y: i16) -> bool {
+        self.update_element(id, |el| {
+            let rect = el.get_bounding_rect();
+            el.set_bounding_rect(BoundingRect { x, y, ..rect })
+        })
+    }
+    pub fn set_size(&mut self, id: ElementId, width: u8, height: u8) -> bool {
+        self.update_element(id, |el| {
+            let rect = el.get_bounding_rect();
+            el.set_bounding_rect(BoundingRect {
+                width,
+                height,
+                ..rect
+            })
+        })
+    }
+    // Lets the element arrange its children, or go back to them placing themselves with None. Returns false
+    // like set_z_index.
+    pub fn set_layout(&mut self, id: ElementId, layout: Option<Layout>) -> bool {
+        self.update_element(id, |el| el.set_layout(layout))
+    }
+    // Keeps the element the size of its content, like text that grows and shrinks as it changes. It stays
+    // put at its x and y. Layout containers size their children anyway. Returns false like set_z_index.
+    pub fn set_fits_content(&mut self, id: ElementId, fits_content: bool) -> bool {
+        self.update_element(id, |el| el.set_fits_content(fits_content))
+    }
+    // How a layout container should size the element. Returns false like set_z_index.
+    pub fn set_layout_sizing(&mut self, id: ElementId, sizing: Sizing) -> bool {
+        self.update_element(id, |el| el.set_layout_sizing(sizing))
+    }
+    // The font text elements use unless they're given their own
+    pub fn font(&self) -> &'static dyn Font {
+        self.font
+    }
+    // How big text would be drawn in the context's font, wrapping at max_width if there is one
+    pub fn measure_text(&self, text: &str, style: TextStyle, max_width: Option<u8>) -> TextSize {
+        measure_text(self.font, text, style, max_width)
+    }
+    // Returns false like set_z_index
+    pub fn set_font(&mut self, id: ElementId, font: &'static dyn Font) -> bool {
+        self.update_element(id, |el| el.set_font(font))
+    }
+    // Returns false like set_z_index
+    pub fn set_shape(&mut self, id: ElementId, shape: Shape) -> bool {
+        self.update_element(id, |el| el.set_shape(shape))
+    }
+    // Returns false like set_z_index
+    pub fn set_shape_style(&mut self, id: ElementId, style: ShapeStyle) -> bool {
+        self.update_element(id, |el| el.set_shape_style(style))
+    }
+    // Returns false like set_z_index
+    pub fn set_image(&mut self, id: ElementId, image: Bitmap<'static>) -> bool {
+        self.update_element(id, |el| el.set_image(image))
+    }
+    // Returns false like set_z_index
+    pub fn set_text_style(&mut self, id: ElementId, style: TextStyle) -> bool {
+        self.update_element(id, |el| el.set_text_style(style))
+    }
+    // Returns false like set_z_index
+    pub fn set_color(&mut self, id: ElementId, color: u8) -> bool {
+        self.update_element(id, |el| el.set_color(color))
+    }
+    // Hidden elements aren't drawn, and neither is anything under them. Returns false like set_z_index, so
+    // the root can't be hidden.
+    pub fn set_visible(&mut self, id: ElementId, visible: bool) -> bool {
+        let Some(location) = self.locate(id) else {
+            return false;
+        };
+        let changed = if visible {
+            self.hidden_elements.remove(&id)
+        } else {
+            self.hidden_elements.insert(id)
+        };
+        if changed {
+            self.invalidate_subtree(id, location.parent_global_x, location.parent_global_y);
+        }
+        true
+    }
+    // Shows and hides the element as visible changes, replacing any earlier binding. Returns false, binding
+    // nothing, if the element isn't in the tree or is the root. Panics if visible is out of listener slots
+    // in a fixed-capacity build.
+    pub fn bind_visibility<O: Observable<bool> + 'static>(
+        &mut self,
+        id: ElementId,
+        visible: &O,
+    ) -> bool {
+        self.try_bind_visibility(id, visible)
+            .expect("no room left for another listener")
+    }
+    // Like bind_visibility, but running out of room is an error and leaves any earlier binding in place
+    pub fn try_bind_visibility<O: Observable<bool> + 'static>(
+        &mut self,
+        id: ElementId,
+        visible: &O,
+    ) -> Result<bool, CapacityError> {
+        if self.locate(id).is_none() {
+            return Ok(false);
+        }
+        let pending = self.pending_visibility.clone();
+        let subscription_id = visible.try_subscribe(move |visible| {
+            let mut pending = pending.borrow_mut();
+            // An element has at most one binding, so there's only ever one pending change per element
+            if let Some(change) = pending.iter_mut().find(|(pending_id, _)| *pending_id == id) {
+                change.1 = visible;
+            } else {
+                pending.push((id, visible));
+            }
+        })?;
+        self.unbind_visibility(id);
+        let unsubscribe = {
+            let visible = visible.clone();
+            Box::new(move || visible.unsubscribe(subscription_id))
+        };
+        self.visibility_bindings.push(VisibilityBinding {
+            element_id: id,
+            unsubscribe,
+        });
+        self.set_visible(id, visible.peek());
+        Ok(true)
+    }
+    // Stops the element following its bound observable, leaving it as it is now
+    pub fn unbind_visibility(&mut self, id: ElementId) {
+        if let Some(i) = self
+            .visibility_bindings
+            .iter()
+            .position(|binding| binding.element_id == id)
+        {
+            (self.visibility_bindings.remove(i).unsubscribe)();
+        }
+        self.pending_visibility
+            .borrow_mut()
+            .retain(|(pending_id, _)| *pending_id != id);
+    }
+    // The element's rect relative to its parent, or None if it isn't mounted
+    pub fn get_rect(&self, id: ElementId) -> Option<BoundingRect> {
+        self.elements.get(id.0).map(|el| el.get_bounding_rect())
+    }
+    // Whether the element itself is shown - it can still be under a hidden ancestor
+    pub fn is_visible(&self, id: ElementId) -> bool {
+        self.elements.get(id.0).is_some() && !self.hidden_elements.contains(&id)
+    }
+    // Gives the children of every layout container their rects. Parents go first, so a container that is
+    // itself laid out gets arranged at its new size.
+    fn apply_layouts(&mut self) {
+        self.scratch_layout_containers.clear();
+        self.scratch_fitted_elements.clear();
+        self.scratch_dfs_stack.clear();
+        self.scratch_dfs_stack.push(ElementStackEntry {
+            element_id: ElementId::ROOT,
+            parent_global_x: 0,
+            parent_global_y: 0,
+            clip: FULL_SCREEN,
+        });
+        while let Some(entry) = self.scratch_dfs_stack.pop() {
+            if self.hidden_elements.contains(&entry.element_id) {
+                continue;
+            }
+            let el = self.elements.get(entry.element_id.0).unwrap();
+            let lays_out_children = el.get_layout().is_some();
+            if lays_out_children {
+                self.scratch_layout_containers.push(entry.element_id);
+            }
+            // Order doesn't matter beyond parents coming before their children
+            let mut child_id = el.get_first_child_id();
+            while let Some(id) = child_id {
+                self.scratch_dfs_stack.push(ElementStackEntry {
+                    element_id: id,
+                    parent_global_x: 0,
+                    parent_global_y: 0,
+                    clip: FULL_SCREEN,
+                });
+                let child = self.elements.get(id.0).unwrap();
+                if !lays_out_children
+                    && child.get_fits_content()
+                    && !self.hidden_elements.contains(&id)
+                {
+                    self.scratch_fitted_elements.push(id);
+                }
+                child_id = child.get_next_element_id();
+            }
+        }
+
+        for i in 0..self.scratch_fitted_elements.len() {
+            let id = self.scratch_fitted_elements[i];
+            let el = self.elements.get(id.0).unwrap();
+            let rect = el.get_bounding_rect();
+            if let Some((width, height)) = el.get_intrinsic_size(self)
+                && (width, height) != (rect.width, rect.height)
+            {
+                self.set_size(id, width, height);
+            }
+        }
+
+        // Measuring children needs the whole context, so the buffer is moved out meanwhile
+        let mut items = core::mem::take(&mut self.scratch_layout_items);
+        for i in 0..self.scratch_layout_containers.len() {
+            let container_id = self.scratch_layout_containers[i];
+            let container = self.elements.get(container_id.0).unwrap();
+            let layout = container.get_layout().unwrap();
+            let rect = container.get_bounding_rect();
+            items.clear();
+            for child_id in self.children(container_id) {
+                if !self.hidden_elements.contains(&child_id) {
+                    items.push(self.layout_item(child_id));
+                }
+            }
+            arrange(&layout, rect.width, rect.height, &mut items);
+            for item in items.iter() {
+                let current = self.elements.get(item.id.0).unwrap().get_bounding_rect();
+                if current != item.rect {
+                    self.set_rect(item.id, item.rect);
+                }
+            }
+        }
+        self.scratch_layout_items = items;
+    }
+    fn layout_item(&self, id: ElementId) -> LayoutItem {
+        let el = self.elements.get(id.0).unwrap();
+        let rect = el.get_bounding_rect();
+        let (natural_width, natural_height) = el
+            .get_intrinsic_size(self)
+            .unwrap_or((rect.width, rect.height));
+        LayoutItem {
+            id,
+            sizing: el.get_layout_sizing(),
+            natural_width,
+            natural_height,
+            rect,
+        }
+    }
+    // Changes an element in place, repainting wherever it and its subtree were and now are on screen.
+    // Returns false if the element isn't in the tree or the update doesn't apply to it.
+    fn update_element(
+        &mut self,
+        id: ElementId,
+        update: impl FnOnce(&mut dyn UIElement) -> bool,
+    ) -> bool {
+        let Some(location) = self.locate(id) else {
+            return false;
+        };
+        let invalidated = self.invalidated_rects.len();
+        let root_redrawing = self
+            .elements_requesting_redraw
+            .borrow()
+            .contains(&ElementId::ROOT);
+        self.invalidate_subtree(id, location.parent_global_x, location.parent_global_y);
+        if !update(&mut **self.elements.get_mut(id.0).unwrap()) {
+            // Nothing changed, so take back the repaint
+            self.invalidated_rects.truncate(invalidated);
+            if !root_redrawing {
+                self.elements_requesting_redraw
+                    .borrow_mut()
+                    .remove(&ElementId::ROOT);
+            }
+            return false;
+        }
+        self.invalidate_subtree(id, location.parent_global_x, location.parent_global_y);
+        true
+    }
+    // The element's children, in sibling order. Empty for unmounted elements.
+    pub fn children(&self, parent: ElementId) -> impl Iterator<Item = ElementId> + '_ {
+        let first = self
+            .elements
+            .get(parent.0)
+            .and_then(|el| el.get_first_child_id());
+        core::iter::successors(first, |id| {
+            self.elements.get(id.0).unwrap().get_next_element_id()
+        })
+    }
+    fn relink(&mut self, id: ElementId, placement: Placement) -> bool {
+        if id == ElementId::ROOT {
+            return false;
+        }
+        let Some(old_location) = self.locate(id) else {
+            return false;
+        };
+        let (Placement::Before(target)
+        | Placement::After(target)
+        | Placement::FirstChildOf(target)
+        | Placement::LastChildOf(target)) = placement;
+        // An element can't end up under itself
+        if self.subtree_contains(id, target) {
+            return false;
+        }
+        let (parent_id, parent_global_x, parent_global_y) = match placement {
+            Placement::Before(sibling) | Placement::After(sibling) => {
+                let Some(location) = self.locate(sibling) else {
+                    return false;
+                };
+                (
+                    location.parent_id,
+                    location.parent_global_x,
+                    location.parent_global_y,
+                )
+            }
+            Placement::FirstChildOf(parent) | Placement::LastChildOf(parent) => {
+                let Some((x, y)) = self.global_origin(parent) else {
+                    return false;
+                };
+                if !self.elements.get(parent.0).unwrap().supports_children() {
+                    return false;
+                }
+                (parent, x, y)
+            }
+        };
+
+        self.invalidate_subtree(
+            id,
+            old_location.parent_global_x,
+            old_location.parent_global_y,
+        );
+        let next_id = self.elements.get(id.0).unwrap().get_next_element_id();
+        if let Some(previous_sibling_id) = old_location.previous_sibling_id {
+            let previous = self.elements.get_mut(previous_sibling_id.0).unwrap();
+            previous.set_next_element_id(next_id);
+        } else {
+            let parent = self.elements.get_mut(old_location.parent_id.0).unwrap();
+            parent.set_first_child_id(next_id);
+        }
+
+        // Found after unlinking, since the element may have been the one before its new spot
+        let previous_sibling_id = match placement {
+            Placement::After(sibling) => Some(sibling),
+            Placement::FirstChildOf(_) => None,
+            Placement::Before(sibling) => {
+                let mut previous = None;
+                for child_id in self.children(parent_id) {
+                    if child_id == sibling {
+                        break;
+                    }
+                    previous = Some(child_id);
+                }
+                previous
+            }
+            Placement::LastChildOf(_) => self.children(parent_id).last(),
+        };
+        if let Some(previous_sibling_id) = previous_sibling_id {
+            let previous = self.elements.get_mut(previous_sibling_id.0).unwrap();
+            let next_id = previous.get_next_element_id();
+            previous.set_next_element_id(Some(id));
+            self.elements
+                .get_mut(id.0)
+                .unwrap()
+                .set_next_element_id(next_id);
+        } else {
+            let parent = self.elements.get_mut(parent_id.0).unwrap();
+            let next_id = parent.get_first_child_id();
+            parent.set_first_child_id(Some(id));
+            self.elements
+                .get_mut(id.0)
+                .unwrap()
+                .set_next_element_id(next_id);
+        }
+        self.invalidate_subtree(id, parent_global_x, parent_global_y);
+        true
+    }
+    // Repaints the area under an element and everything below it, wherever they sit on screen
+    fn invalidate_subtree(&mut self, id: ElementId, parent_global_x: i16, parent_global_y: i16) {
+        self.scratch_dfs_stack.clear();
+        self.scratch_dfs_stack.push(ElementStackEntry {
+            element_id: id,
+            parent_global_x,
+            parent_global_y,
+            clip: FULL_SCREEN,
+        });
+        while let Some(entry) = self.scratch_dfs_stack.pop() {
+            let el = self.elements.get(entry.element_id.0).unwrap();
+            let mut rect = el.get_bounding_rect();
+            rect.x += entry.parent_global_x;
+            rect.y += entry.parent_global_y;
+            let mut child_id = el.get_first_child_id();
+            while let Some(id) = child_id {
+                self.scratch_dfs_stack.push(ElementStackEntry {
+                    element_id: id,
+                    parent_global_x: rect.x,
+                    parent_global_y: rect.y,
+                    clip: FULL_SCREEN,
+                });
+                child_id = self.elements.get(id.0).unwrap().get_next_element_id();
+            }
+            self.invalidate_rect(rect);
+        }
+    }
+    fn subtree_contains(&mut self, root: ElementId, target: ElementId) -> bool {
+        self.scratch_dfs_stack.clear();
+        self.scratch_dfs_stack.push(ElementStackEntry {
+            element_id: root,
+            parent_global_x: 0,
+            parent_global_y: 0,
+            clip: FULL_SCREEN,
+        });
+        while let Some(entry) = self.scratch_dfs_stack.pop() {
+            if entry.element_id == target {
+                return true;
+            }
+            let mut child_id = self
+                .elements
+                .get(entry.element_id.0)
+                .unwrap()
+                .get_first_child_id();
+            while let Some(id) = child_id {
+                self.scratch_dfs_stack.push(ElementStackEntry {
+                    element_id: id,
+                    parent_global_x: 0,
+                    parent_global_y: 0,
+                    clip: FULL_SCREEN,
+                });
+                child_id = self.elements.get(id.0).unwrap().get_next_element_id();
+            }
+        }
+        false
+    }
+    // Where an element's own coordinates start on screen
+    fn global_origin(&mut self, id: ElementId) -> Option<(i16, i16)> {
+        if id == ElementId::ROOT {
+            return Some((0, 0));
+        }
+        let location = self.locate(id)?;
+        let rect = self.elements.get(id.0).unwrap().get_bounding_rect();
+        Some((
+            location.parent_global_x + rect.x,
+            location.parent_global_y + rect.y,
+        ))
+    }
+    // Repaints whatever is on screen within rect on the next handle_draw_requests
+    pub fn invalidate_rect(&mut self, rect: BoundingRect) {
+        if self.invalidated_rects.try_push(rect).is_err() {
+            // Out of room to track it separately, so repaint from the root instead
+            self.elements_requesting_redraw
+                .borrow_mut()
+                .insert(ElementId::ROOT);
+        }
+    }
+    // Finds where an element hangs in the tree by walking down from the root
+    fn locate(&mut self, id: ElementId) -> Option<ElementLocation> {
+        self.scratch_dfs_stack.clear();
+        self.scratch_dfs_stack.push(ElementStackEntry {
+            element_id: ElementId::ROOT,
+            parent_global_x: 0,
+            parent_global_y: 0,
+            clip: FULL_SCREEN,
+        });
+        while let Some(entry) = self.scratch_dfs_stack.pop() {
+            let el = self.elements.get(entry.element_id.0).unwrap();
+            let rect = el.get_bounding_rect();
+            let global_x = entry.parent_global_x + rect.x;
+            let global_y = entry.parent_global_y + rect.y;
+            let mut previous_sibling_id = None;
+            let mut next_child_id = el.get_first_child_id();
+            while let Some(child_id) = next_child_id {
+                if child_id == id {
+                    return Some(ElementLocation {
+                        parent_id: entry.element_id,
+                        previous_sibling_id,
+                        parent_global_x: global_x,
+                        parent_global_y: global_y,
+                    });
+                }
+                self.scratch_dfs_stack.push(ElementStackEntry {
+                    element_id: child_id,
+                    parent_global_x: global_x,
+                    parent_global_y: global_y,
+                    clip: FULL_SCREEN,
+                });
+                previous_sibling_id = Some(child_id);
+                next_child_id = self.elements.get(child_id.0).unwrap().get_next_element_id();
+            }
+        }
+        None
+    }
+    pub fn set_window_limits(&mut self, limits: WindowLimits) {
+        self.window_limits = limits;
+    }
+    pub fn get_screen_buffer(&self) -> &[u8] {
         &self.screen_buffer
     }
-    pub fn handle_draw_requests(&mut self) {
+    // Saves the screen as a binary PBM, which shares the buffer's packing and 1 = black convention
+    #[cfg(feature = "std")]
+    pub fn write_pbm<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "P4\n{} {}\n", SCREEN_WIDTH, SCREEN_HEIGHT)?;
+        out.write_all(&self.screen_buffer)
+    }
+    // Repaints everything that requested a redraw, and reports which parts of the screen buffer changed
+    pub fn handle_draw_requests(&mut self) -> DrawReport<'_> {
+        loop {
+            let change = self.pending_visibility.borrow_mut().pop();
+            let Some((id, visible)) = change else {
+                break;
+            };
+            self.set_visible(id, visible);
+        }
+        // Picks up text that changed length and anything else that changed size since the last frame
+        self.apply_layouts();
         self.scratch_redraw_sources.clear();
 
         let mut elements_requesting_redraw = self.elements_requesting_redraw.borrow_mut();
@@ -134,85 +947,129 @@ impl UIContext {
         let partial_area_limit = (SCREEN_HEIGHT as usize * SCREEN_WIDTH as usize) / 4;
         let mut tracked_area: usize = 0;
 
-        let ordered_elements = {
-            struct ElementTreeNode {
-                element_id: usize,
-                global_x: i16,
-                global_y: i16,
-            }
-            let mut ordered_elements =
-                Vec::<ElementTreeNode>::with_capacity(self.elements.data.len());
-
-            struct ElementStackEntry {
-                element_id: usize,
-                parent_global_x: i16,
-                parent_global_y: i16,
+        self.scratch_ordered_elements.clear();
+        self.scratch_dfs_stack.clear();
+        self.scratch_dfs_stack.push(ElementStackEntry {
+            element_id: ElementId::ROOT,
+            parent_global_x: 0,
+            parent_global_y: 0,
+            clip: FULL_SCREEN,
+        });
+        while let Some(curr_entry) = self.scratch_dfs_stack.pop() {
+            let curr_id = curr_entry.element_id;
+            if self.hidden_elements.contains(&curr_id) {
+                continue;
             }
-            let mut dfs_stack = Vec::<ElementStackEntry>::new();
-            dfs_stack.push(ElementStackEntry {
-                element_id: 0,
-                parent_global_x: 0,
-                parent_global_y: 0,
+            let el = self.elements.get(curr_id.0).unwrap();
+            let rect = el.get_bounding_rect();
+            let global_x = curr_entry.parent_global_x + rect.x;
+            let global_y = curr_entry.parent_global_y + rect.y;
+            // Every live element has a distinct id, so these can't outgrow the element store
+            self.scratch_ordered_elements.push(ElementTreeNode {
+                element_id: curr_id,
+                global_x,
+                global_y,
+                clip: curr_entry.clip,
             });
-            while !dfs_stack.is_empty() {
-                let curr_entry = dfs_stack.pop().unwrap();
-                let mut curr_id = curr_entry.element_id;
-                let el = self.elements.get(curr_id).unwrap();
-                let rect = el.get_bounding_rect();
-                let global_x = curr_entry.parent_global_x + rect.x;
-                let global_y = curr_entry.parent_global_y + rect.y;
-                ordered_elements.push(ElementTreeNode {
-                    element_id: curr_id,
-                    global_x,
-                    global_y,
-                });
-                if elements_requesting_redraw.contains(&curr_id) {
-                    let mut global_rect = rect;
-                    global_rect.x = global_x;
-                    global_rect.y = global_y;
-                    tracked_area = tracked_area.saturating_add(
-                        (global_rect.width as usize) * (global_rect.height as usize),
-                    );
-                    self.scratch_redraw_sources.push(global_rect);
+            let global_rect = BoundingRect {
+                x: global_x,
+                y: global_y,
+                ..rect
+            };
+            if elements_requesting_redraw.contains(&curr_id) {
+                // Only the part that can show needs repainting
+                if let Some(visible_rect) = global_rect.intersection(&curr_entry.clip) {
+                    tracked_area = tracked_area.saturating_add(visible_rect.area());
+                    self.scratch_redraw_sources.push(visible_rect);
                 }
-                curr_id = el.get_first_child_id();
-                while curr_id != 0 {
-                    dfs_stack.push(ElementStackEntry {
-                        element_id: curr_id,
-                        parent_global_x: global_x,
-                        parent_global_y: global_y,
-                    });
-                    curr_id = self.elements.get(curr_id).unwrap().get_next_element_id();
+            }
+            let child_clip = if el.get_clips_children() {
+                global_rect
+                    .intersection(&curr_entry.clip)
+                    .unwrap_or(NOWHERE)
+            } else {
+                curr_entry.clip
+            };
+            // Painter's order: a parent, then each child's subtree, by z-index and then sibling order.
+            // The stack pops from the end, so the children go on back to front.
+            let first_sibling = self.scratch_dfs_stack.len();
+            let mut child_id = el.get_first_child_id();
+            while let Some(id) = child_id {
+                self.scratch_dfs_stack.push(ElementStackEntry {
+                    element_id: id,
+                    parent_global_x: global_x,
+                    parent_global_y: global_y,
+                    clip: child_clip,
+                });
+                child_id = self.elements.get(id.0).unwrap().get_next_element_id();
+            }
+            let siblings = &mut self.scratch_dfs_stack[first_sibling..];
+            siblings.reverse();
+            // Stable insertion sort - sibling lists are short, and this doesn't allocate
+            let z_index = |entry: &ElementStackEntry| {
+                self.elements.get(entry.element_id.0).unwrap().get_z_index()
+            };
+            for i in 1..siblings.len() {
+                let mut j = i;
+                while j > 0 && z_index(&siblings[j - 1]) < z_index(&siblings[j]) {
+                    siblings.swap(j - 1, j);
+                    j -= 1;
                 }
             }
+        }
 
-            ordered_elements
-        };
+        let mut rects_fit = true;
+        for rect in self.invalidated_rects.iter() {
+            tracked_area = tracked_area.saturating_add(rect.area());
+            rects_fit &= self.scratch_redraw_sources.try_push(*rect).is_ok();
+        }
+        self.invalidated_rects.clear();
 
-        let doing_full_redraw =
-            elements_requesting_redraw.len() > 16 || tracked_area > partial_area_limit;
+        let mut doing_full_redraw = elements_requesting_redraw.len() > 16
+            || tracked_area > partial_area_limit
+            || !rects_fit;
 
         self.scratch_optimized_regions.clear();
-        if doing_full_redraw {
-            self.scratch_optimized_regions.push(BoundingRect {
-                x: 0,
-                y: 0,
-                width: SCREEN_WIDTH,
-                height: SCREEN_HEIGHT,
-            });
-        } else {
-            sweep_merge_rectangles(
+        if !doing_full_redraw {
+            // Too many fragments to track is as good as a full redraw
+            doing_full_redraw = sweep_merge_rectangles(
                 &self.scratch_redraw_sources,
                 &mut self.scratch_optimized_regions,
                 &mut self.scratch_sweep_normalized,
                 &mut self.scratch_sweep_x_edges,
                 &mut self.scratch_sweep_y_spans,
-            );
+            )
+            .is_err();
+        }
+        if doing_full_redraw {
+            self.scratch_optimized_regions.clear();
+            self.scratch_optimized_regions.push(FULL_SCREEN);
         }
 
-        for el_node in ordered_elements {
+        // Elements paint straight into the buffer, so move it out while they borrow the context
+        let mut screen_buffer = core::mem::take(&mut self.screen_buffer);
+        // Start from a blank background, so transparent and blended pixels composite the same way
+        // whether this is a full or a partial redraw
+        for region in self.scratch_optimized_regions.iter() {
+            let Some((x0, x1, y0, y1)) = normalize_rect_to_screen(region) else {
+                continue;
+            };
+            for y in y0..y1 {
+                let row_start = y as usize * ROW_BYTES;
+                let mut span = RowSpan {
+                    row: &mut screen_buffer[row_start..row_start + ROW_BYTES],
+                    origin_x: 0,
+                    start: x0,
+                    end: x1,
+                    mode: CompositeMode::Normal,
+                };
+                span.fill(x0 as u8, x1 as u8, 0);
+            }
+        }
+        for i in 0..self.scratch_ordered_elements.len() {
+            let el_node = self.scratch_ordered_elements[i];
             let id = el_node.element_id;
-            let el = self.elements.get(id).unwrap();
+            let el = self.elements.get(id.0).unwrap();
             let local_rect = el.get_bounding_rect();
             let rect = BoundingRect {
                 x: el_node.global_x,
@@ -220,13 +1077,17 @@ impl UIContext {
                 width: local_rect.width,
                 height: local_rect.height,
             };
+            let Some(visible_rect) = rect.intersection(&el_node.clip) else {
+                continue;
+            };
             let regions_iter: &[_] =
                 if doing_full_redraw || elements_requesting_redraw.contains(&id) {
-                    core::slice::from_ref(&rect)
+                    core::slice::from_ref(&visible_rect)
                 } else {
                     self.scratch_region_intersections.clear();
                     for region in self.scratch_optimized_regions.iter() {
-                        if let Some(intersect) = region.intersection(&rect) {
+                        if let Some(intersect) = region.intersection(&visible_rect) {
+                            // The regions don't overlap, so there are never more pieces than regions
                             self.scratch_region_intersections.push(intersect);
                         }
                     }
@@ -237,50 +1098,238 @@ impl UIContext {
                 };
 
             for region in regions_iter {
-                for y in
-                    region.y.max(0)..(region.y + region.height as i16).min(SCREEN_HEIGHT as i16)
-                {
-                    for x in
-                        region.x.max(0)..(region.x + region.width as i16).min(SCREEN_WIDTH as i16)
-                    {
-                        let idx = (y as usize) * (SCREEN_WIDTH as usize) + (x as usize);
-                        let byte_idx = idx / 8;
-                        let bit_idx = 7 - (idx % 8);
-                        if byte_idx < self.screen_buffer.len() {
-                            let pixel = el.get_pixel(
-                                self,
-                                (x as i16 - rect.x) as u8,
-                                (y as i16 - rect.y) as u8,
-                            );
-                            if pixel != 0 {
-                                self.screen_buffer[byte_idx] |= 1 << bit_idx;
-                            } else {
-                                self.screen_buffer[byte_idx] &= !(1 << bit_idx);
-                            }
-                        }
-                    }
+                let Some((x0, x1, y0, y1)) = normalize_rect_to_screen(region) else {
+                    continue;
+                };
+                for y in y0..y1 {
+                    let row_start = y as usize * ROW_BYTES;
+                    let mut span = RowSpan {
+                        row: &mut screen_buffer[row_start..row_start + ROW_BYTES],
+                        origin_x: rect.x,
+                        start: x0,
+                        end: x1,
+                        mode: el.get_composite_mode(),
+                    };
+                    el.draw_row(self, (y - rect.y) as u8, &mut span);
                 }
             }
         }
+        self.screen_buffer = screen_buffer;
 
         elements_requesting_redraw.clear();
+        drop(elements_requesting_redraw);
+
+        let aligned = align_windows(
+            &self.scratch_optimized_regions,
+            &self.window_limits,
+            &mut self.scratch_aligned_regions,
+            &mut self.scratch_aligned_sources,
+            &mut self.scratch_sweep_normalized,
+            &mut self.scratch_sweep_x_edges,
+            &mut self.scratch_sweep_y_spans,
+        );
+        if aligned.is_err() {
+            self.scratch_aligned_regions.clear();
+            self.scratch_aligned_regions.push(FULL_SCREEN);
+        }
+
+        DrawReport {
+            full_refresh: doing_full_redraw,
+            regions: &self.scratch_optimized_regions,
+            aligned_regions: &self.scratch_aligned_regions,
+            screen_buffer: &self.screen_buffer,
+        }
+    }
+}
+
+// What a handle_draw_requests call changed, for display drivers deciding between full and partial refreshes
+pub struct DrawReport<'a> {
+    // Everything was repainted - regions then holds a single full-screen rect
+    pub full_refresh: bool,
+    // Non-overlapping dirty rects in screen coordinates, clipped to the screen. Empty if nothing changed.
+    pub regions: &'a [BoundingRect],
+    // The regions widened to the window limits' column alignment and merged down to at most max_windows
+    // rects. These are what a panel with byte-granular RAM windows should be sent.
+    pub aligned_regions: &'a [BoundingRect],
+    pub screen_buffer: &'a [u8],
+}
+
+impl DrawReport<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+    pub fn windows(&self) -> impl Iterator<Item = BufferWindow> + '_ {
+        self.aligned_regions.iter().map(BufferWindow::covering)
+    }
+}
+
+// Constraints of the panel's partial update windows
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowLimits {
+    // Windows start and end on multiples of this many pixels - 8 for controllers addressing RAM by the byte
+    pub column_alignment: u8,
+    // Windows get merged until there are at most this many (and always at least one)
+    pub max_windows: usize,
+}
+
+impl Default for WindowLimits {
+    fn default() -> Self {
+        WindowLimits {
+            column_alignment: 8,
+            max_windows: 8,
+        }
+    }
+}
+
+// The bytes of the screen buffer that hold a region: the same byte columns in each of its rows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BufferWindow {
+    pub rows: Range<usize>,
+    pub byte_columns: Range<usize>,
+}
+
+impl BufferWindow {
+    pub fn covering(rect: &BoundingRect) -> BufferWindow {
+        match normalize_rect_to_screen(rect) {
+            Some((x0, x1, y0, y1)) => BufferWindow {
+                rows: y0 as usize..y1 as usize,
+                byte_columns: x0 as usize / 8..(x1 as usize).div_ceil(8),
+            },
+            None => BufferWindow {
+                rows: 0..0,
+                byte_columns: 0..0,
+            },
+        }
+    }
+    // Smallest contiguous range of the buffer containing the window
+    pub fn byte_range(&self) -> Range<usize> {
+        if self.rows.is_empty() {
+            return 0..0;
+        }
+        self.rows.start * ROW_BYTES + self.byte_columns.start
+            ..(self.rows.end - 1) * ROW_BYTES + self.byte_columns.end
+    }
+    pub fn row_byte_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.rows
+            .clone()
+            .map(|y| y * ROW_BYTES + self.byte_columns.start..y * ROW_BYTES + self.byte_columns.end)
     }
 }
 
 // TODO: a lot of these functions should be internal only
 pub trait UIElement {
-    fn mount_to_context(&self, ctx: &UIContext, id: usize);
-    // Coordinates are in element space. width and height describes size of drawn region, not size of element
+    // Called once the element has its id. Fails if there's no room for what it needs, like a listener on
+    // its text, in a fixed-capacity build - the element isn't mounted then.
+    fn mount_to_context(&self, ctx: &UIContext, id: ElementId) -> Result<(), CapacityError>;
+    // Coordinates are in element space. width and height describes size of drawn region, not size of element.
+    // Returns 0, 1 or TRANSPARENT.
     fn get_pixel(&self, ctx: &UIContext, x: u8, y: u8) -> u8;
+    // Paints element-space row y over the columns covered by span. Override this to write whole bytes
+    // at a time - the default falls back to a get_pixel call per pixel.
+    fn draw_row(&self, ctx: &UIContext, y: u8, span: &mut RowSpan) {
+        let (start, end) = (span.start() as i16, span.end() as i16);
+        // Gathers a screen byte's worth of pixels at a time, so each byte is blended once
+        let mut byte_x = start - (span.origin_x + start).rem_euclid(8);
+        while byte_x < end {
+            let (mut bits, mut opaque) = (0u8, 0u8);
+            for x in max(byte_x, start)..min(byte_x + 8, end) {
+                let bit = 0x80 >> (x - byte_x);
+                match self.get_pixel(ctx, x as u8, y) {
+                    TRANSPARENT => continue,
+                    0 => {}
+                    _ => bits |= bit,
+                }
+                opaque |= bit;
+            }
+            span.write_byte_masked(byte_x, bits, opaque);
+            byte_x += 8;
+        }
+    }
     fn get_bounding_rect(&self) -> BoundingRect;
-    // 0 means null here
-    fn get_first_child_id(&self) -> usize;
-    fn get_next_element_id(&self) -> usize;
-    fn set_next_element_id(&mut self, id: usize);
-    fn insert_child_at_end(&mut self, ctx: &mut UIContext, id: usize);
+    // The setters return false, changing nothing, if the element doesn't have that property
+    fn set_bounding_rect(&mut self, _rect: BoundingRect) -> bool {
+        false
+    }
+    fn set_color(&mut self, _color: u8) -> bool {
+        false
+    }
+    fn set_text_style(&mut self, _style: TextStyle) -> bool {
+        false
+    }
+    fn set_font(&mut self, _font: &'static dyn Font) -> bool {
+        false
+    }
+    fn set_image(&mut self, _image: Bitmap<'static>) -> bool {
+        false
+    }
+    fn set_shape(&mut self, _shape: Shape) -> bool {
+        false
+    }
+    fn set_shape_style(&mut self, _style: ShapeStyle) -> bool {
+        false
+    }
+    // The size the element would like to be, if it depends on its content
+    fn get_intrinsic_size(&self, _ctx: &UIContext) -> Option<(u8, u8)> {
+        None
+    }
+    // How the element is sized when its parent lays it out
+    fn get_layout_sizing(&self) -> Sizing {
+        Sizing::default()
+    }
+    fn set_layout_sizing(&mut self, _sizing: Sizing) -> bool {
+        false
+    }
+    // Whether the element resizes to its intrinsic size by itself, when its parent doesn't lay it out
+    fn get_fits_content(&self) -> bool {
+        false
+    }
+    fn set_fits_content(&mut self, _fits_content: bool) -> bool {
+        false
+    }
+    // How the element arranges its children, if it does
+    fn get_layout(&self) -> Option<Layout> {
+        None
+    }
+    fn set_layout(&mut self, _layout: Option<Layout>) -> bool {
+        false
+    }
+    // Whether elements can be mounted or moved under this one
+    fn supports_children(&self) -> bool {
+        false
+    }
+    fn get_first_child_id(&self) -> Option<ElementId>;
+    fn set_first_child_id(&mut self, _id: Option<ElementId>) {
+        panic!("element does not support children");
+    }
+    fn get_next_element_id(&self) -> Option<ElementId>;
+    fn set_next_element_id(&mut self, id: Option<ElementId>);
+    fn insert_child_at_end(&mut self, ctx: &mut UIContext, id: ElementId);
+    // Siblings with a higher z-index paint over those with a lower one, whatever their order
+    fn get_z_index(&self) -> i8 {
+        0
+    }
+    fn set_z_index(&mut self, _z_index: i8) -> bool {
+        false
+    }
+    // Whether children are cut off at the element's bounding rect, rather than being free to paint outside it
+    fn get_clips_children(&self) -> bool {
+        false
+    }
+    fn set_clips_children(&mut self, _clips_children: bool) -> bool {
+        false
+    }
+    // How the element's pixels combine with what's already been painted under it
+    fn get_composite_mode(&self) -> CompositeMode {
+        CompositeMode::Normal
+    }
+    fn set_composite_mode(&mut self, _mode: CompositeMode) -> bool {
+        false
+    }
+    // Undo whatever mount_to_context hooked up, like signal subscriptions
+    fn unmount_from_context(&self, _ctx: &UIContext) {}
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct BoundingRect {
     pub x: i16,
     pub y: i16,
@@ -302,6 +1351,22 @@ impl BoundingRect {
             && self.y + self.height as i16 > other.y
     }
 
+    pub fn union(&self, other: &BoundingRect) -> BoundingRect {
+        let x0 = min(self.x, other.x);
+        let y0 = min(self.y, other.y);
+        let x1 = max(self.x + self.width as i16, other.x + other.width as i16);
+        let y1 = max(self.y + self.height as i16, other.y + other.height as i16);
+        BoundingRect {
+            x: x0,
+            y: y0,
+            width: (x1 - x0) as u8,
+            height: (y1 - y0) as u8,
+        }
+    }
+    fn area(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
     pub fn intersection(&self, other: &BoundingRect) -> Option<BoundingRect> {
         let x0 = core::cmp::max(self.x, other.x);
         let y0 = core::cmp::max(self.y, other.y);
@@ -321,6 +1386,99 @@ impl BoundingRect {
     }
 }
 
+// What an opaque pixel does to the one already on screen. TRANSPARENT pixels never change anything.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompositeMode {
+    // Replaces it
+    #[default]
+    Normal,
+    // 1 sets it, 0 leaves it - clear pixels are see-through, like text laid over a pattern
+    Or,
+    // 1 inverts it, 0 leaves it - for highlighting whatever is underneath
+    Xor,
+    // 0 clears it, 1 leaves it - for cutting shapes out of what's underneath
+    Mask,
+}
+
+// A run of one packed screen row (MSB is the leftmost pixel) that an element paints into.
+// Methods take element-space x coordinates, clip to the run, and blend using the element's composite mode.
+pub struct RowSpan<'a> {
+    row: &'a mut [u8],
+    // Screen x of the element's x = 0
+    origin_x: i16,
+    // Screen columns covered by the run, end exclusive
+    start: i16,
+    end: i16,
+    mode: CompositeMode,
+}
+
+impl RowSpan<'_> {
+    pub fn start(&self) -> u8 {
+        (self.start - self.origin_x) as u8
+    }
+    pub fn end(&self) -> u8 {
+        (self.end - self.origin_x) as u8
+    }
+    pub fn set_pixel(&mut self, x: u8, color: u8) {
+        self.fill(x, x.saturating_add(1), color);
+    }
+    pub fn fill(&mut self, from: u8, to: u8, color: u8) {
+        let x0 = max(self.start, self.origin_x + from as i16);
+        let x1 = min(self.end, self.origin_x + to as i16);
+        if x0 >= x1 || color == TRANSPARENT {
+            return;
+        }
+        let bits = if color != 0 { 0xFF } else { 0 };
+        for byte_idx in (x0 / 8) as usize..=((x1 - 1) / 8) as usize {
+            self.blend(byte_idx, bits, byte_mask(byte_idx, x0, x1));
+        }
+    }
+    // Writes 8 pixels starting at x, taken from bits MSB first
+    pub fn write_byte(&mut self, x: i16, bits: u8) {
+        self.write_byte_masked(x, bits, 0xFF);
+    }
+    // Like write_byte, but only the pixels set in opaque are written - the rest are transparent
+    pub fn write_byte_masked(&mut self, x: i16, bits: u8, opaque: u8) {
+        let gx = self.origin_x + x;
+        let x0 = max(self.start, gx);
+        let x1 = min(self.end, gx + 8);
+        if x0 >= x1 {
+            return;
+        }
+        let shift = gx.rem_euclid(8) as u32;
+        let placed = ((bits as u16) << 8 >> shift).to_be_bytes();
+        let placed_opaque = ((opaque as u16) << 8 >> shift).to_be_bytes();
+        let first_byte = gx.div_euclid(8);
+        for i in 0..2 {
+            let byte_idx = first_byte + i as i16;
+            if byte_idx < x0 / 8 || byte_idx > (x1 - 1) / 8 {
+                continue;
+            }
+            let byte_idx = byte_idx as usize;
+            let mask = byte_mask(byte_idx, x0, x1) & placed_opaque[i];
+            self.blend(byte_idx, placed[i], mask);
+        }
+    }
+    // Combines bits into the pixels of row byte byte_idx selected by mask
+    fn blend(&mut self, byte_idx: usize, bits: u8, mask: u8) {
+        let under = self.row[byte_idx];
+        self.row[byte_idx] = match self.mode {
+            CompositeMode::Normal => (under & !mask) | (bits & mask),
+            CompositeMode::Or => under | (bits & mask),
+            CompositeMode::Xor => under ^ (bits & mask),
+            CompositeMode::Mask => under & (bits | !mask),
+        };
+    }
+}
+
+// Bits of row byte byte_idx that fall within screen columns [x0, x1)
+fn byte_mask(byte_idx: usize, x0: i16, x1: i16) -> u8 {
+    let byte_x = byte_idx as i16 * 8;
+    let lo = (x0 - byte_x).clamp(0, 8);
+    let hi = (x1 - byte_x).clamp(0, 8);
+    ((0xFFu16 >> lo) & !(0xFFu16 >> hi)) as u8
+}
+
 fn normalize_rect_to_screen(rect: &BoundingRect) -> Option<(i16, i16, i16, i16)> {
     let x0 = max(0, rect.x);
     let y0 = max(0, rect.y);
@@ -334,32 +1492,32 @@ fn normalize_rect_to_screen(rect: &BoundingRect) -> Option<(i16, i16, i16, i16)>
     }
 }
 
-fn sweep_merge_rectangles(
+fn sweep_merge_rectangles<const R: usize, const E: usize>(
     rects: &[BoundingRect],
-    out: &mut Vec<BoundingRect>,
-    normalized: &mut Vec<BoundingRect>,
-    x_edges: &mut Vec<i16>,
-    y_spans: &mut Vec<(i16, i16)>,
-) {
+    out: &mut CapacityVec<BoundingRect, R>,
+    normalized: &mut CapacityVec<BoundingRect, R>,
+    x_edges: &mut CapacityVec<i16, E>,
+    y_spans: &mut CapacityVec<(i16, i16), R>,
+) -> Result<(), CapacityError> {
     out.clear();
     normalized.clear();
     x_edges.clear();
 
     for rect in rects {
         if let Some((x0, x1, y0, y1)) = normalize_rect_to_screen(rect) {
-            normalized.push(BoundingRect {
+            normalized.try_push(BoundingRect {
                 x: x0,
                 y: y0,
                 width: (x1 - x0) as u8,
                 height: (y1 - y0) as u8,
-            });
-            x_edges.push(x0);
-            x_edges.push(x1);
+            })?;
+            x_edges.try_push(x0)?;
+            x_edges.try_push(x1)?;
         }
     }
 
     if normalized.is_empty() {
-        return;
+        return Ok(());
     }
 
     x_edges.sort_unstable();
@@ -378,7 +1536,7 @@ fn sweep_merge_rectangles(
         y_spans.clear();
         for rect in normalized.iter() {
             if rect.x <= x_start && rect.x + rect.width as i16 >= x_end {
-                y_spans.push((rect.y, rect.y + rect.height as i16));
+                y_spans.try_push((rect.y, rect.y + rect.height as i16))?;
             }
         }
 
@@ -393,101 +1551,637 @@ fn sweep_merge_rectangles(
             if span.0 <= current_span.1 {
                 current_span.1 = max(current_span.1, span.1);
             } else {
-                out.push(BoundingRect {
+                out.try_push(BoundingRect {
                     x: x_start,
                     y: current_span.0,
                     width: (x_end - x_start) as u8,
                     height: (current_span.1 - current_span.0) as u8,
-                });
+                })?;
                 current_span = *span;
             }
         }
 
-        out.push(BoundingRect {
+        out.try_push(BoundingRect {
             x: x_start,
             y: current_span.0,
             width: (x_end - x_start) as u8,
             height: (current_span.1 - current_span.0) as u8,
+        })?;
+    }
+    Ok(())
+}
+
+// Widens on-screen regions out to the column alignment, then merges them back into non-overlapping windows
+fn align_windows<const R: usize, const E: usize>(
+    regions: &[BoundingRect],
+    limits: &WindowLimits,
+    out: &mut CapacityVec<BoundingRect, R>,
+    aligned: &mut CapacityVec<BoundingRect, R>,
+    normalized: &mut CapacityVec<BoundingRect, R>,
+    x_edges: &mut CapacityVec<i16, E>,
+    y_spans: &mut CapacityVec<(i16, i16), R>,
+) -> Result<(), CapacityError> {
+    let alignment = max(limits.column_alignment, 1) as i16;
+    aligned.clear();
+    for region in regions {
+        let x0 = region.x.div_euclid(alignment) * alignment;
+        let x1 = min(
+            (region.x + region.width as i16 + alignment - 1).div_euclid(alignment) * alignment,
+            SCREEN_WIDTH as i16,
+        );
+        aligned.try_push(BoundingRect {
+            x: x0,
+            y: region.y,
+            width: (x1 - x0) as u8,
+            height: region.height,
+        })?;
+    }
+
+    // Widening makes neighbours overlap, so sweep them apart again. Every edge is aligned now, so the strips are too.
+    sweep_merge_rectangles(aligned, out, normalized, x_edges, y_spans)?;
+
+    // The sweep cuts at every edge - join strips back up where they line up exactly
+    let mut i = 0;
+    while i < out.len() {
+        let rect = out[i];
+        let neighbour = out.iter().position(|other| {
+            other.x == rect.x + rect.width as i16
+                && other.y == rect.y
+                && other.height == rect.height
         });
+        if let Some(j) = neighbour {
+            out[i].width += out[j].width;
+            out.remove(j);
+            if j < i {
+                i -= 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    // Merge whichever pair wastes the least area until we're under the limit
+    let max_windows = max(limits.max_windows, 1);
+    while out.len() > max_windows {
+        let mut best = (0, 1, usize::MAX);
+        for i in 0..out.len() {
+            for j in i + 1..out.len() {
+                let waste = out[i]
+                    .union(&out[j])
+                    .area()
+                    .saturating_sub(out[i].area() + out[j].area());
+                if waste < best.2 {
+                    best = (i, j, waste);
+                }
+            }
+        }
+        let (mut i, j, _) = best;
+        out[i] = out[i].union(&out[j]);
+        out.remove(j);
+        // The union can swallow parts of other windows - absorb those so windows never overlap
+        while let Some(k) = (0..out.len()).find(|&k| k != i && out[k].overlaps(&out[i])) {
+            out[i] = out[i].union(&out[k]);
+            out.remove(k);
+            if k < i {
+                i -= 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+// What a text element worked out last time, so it's only redone when something changes
+#[derive(Default)]
+struct TextCache {
+    // The element's text drawn at its size, packed like the screen buffer. This is on the heap even with
+    // fixed-capacity. Drawing allocates it the first time, and again only if the element has grown.
+    bitmap: Vec<u8>,
+    row_bytes: usize,
+    rendered: bool,
+    intrinsic_size: Option<(u8, u8)>,
+}
+
+impl TextCache {
+    // Keeps the bitmap's allocation for the next render
+    fn clear(&mut self) {
+        self.rendered = false;
+        self.intrinsic_size = None;
     }
 }
 
 pub struct TextUIElement<TextObservable: Observable<String>> {
     text: TextObservable,
+    // Set by the text subscription, and checked before the cache is used
+    text_changed: Rc<Cell<bool>>,
+    cache: RefCell<TextCache>,
     rect: BoundingRect,
-    next_element_id: usize,
+    style: TextStyle,
+    // Falls back to the context's font
+    font: Option<&'static dyn Font>,
+    fits_content: bool,
+    next_element_id: Option<ElementId>,
+    z_index: i8,
+    composite_mode: CompositeMode,
+    layout_sizing: Sizing,
+    subscription_id: Cell<Option<SubscriptionId>>,
 }
 
 impl<TO: Observable<String>> TextUIElement<TO> {
     pub fn new(text: &TO, rect: BoundingRect) -> TextUIElement<TO> {
+        TextUIElement::with_style(text, rect, TextStyle::default())
+    }
+    pub fn with_style(text: &TO, rect: BoundingRect, style: TextStyle) -> TextUIElement<TO> {
         TextUIElement {
             text: text.clone(),
+            text_changed: Rc::new(Cell::new(false)),
+            cache: RefCell::new(TextCache::default()),
             rect,
-            next_element_id: 0,
+            style,
+            font: None,
+            fits_content: false,
+            next_element_id: None,
+            z_index: 0,
+            composite_mode: CompositeMode::Normal,
+            layout_sizing: Sizing::default(),
+            subscription_id: Cell::new(None),
+        }
+    }
+    fn font(&self, ctx: &UIContext) -> &'static dyn Font {
+        self.font.unwrap_or(ctx.font)
+    }
+    fn line_height(&self, ctx: &UIContext) -> i16 {
+        line_height(self.font(ctx), self.style.scale)
+    }
+    // The lines that fit in the element. There's always room for one, even if it's cut off at the bottom.
+    fn lines<'a>(&self, ctx: &UIContext, text: &'a str) -> Lines<'a, impl Fn(char) -> i16> {
+        let max_lines = max(
+            1,
+            self.rect.height as usize / self.line_height(ctx) as usize,
+        );
+        Lines::new(
+            text,
+            self.style,
+            self.rect.width as i16,
+            max_lines,
+            advance(self.font(ctx), self.style.scale),
+        )
+    }
+    fn cache(&self) -> RefMut<'_, TextCache> {
+        let mut cache = self.cache.borrow_mut();
+        if self.text_changed.replace(false) {
+            cache.clear();
+        }
+        cache
+    }
+    // The cache with the bitmap rendered
+    fn rendered(&self, ctx: &UIContext) -> RefMut<'_, TextCache> {
+        let mut cache = self.cache();
+        if !cache.rendered {
+            self.render(ctx, &mut cache);
+        }
+        cache
+    }
+    fn render(&self, ctx: &UIContext, cache: &mut TextCache) {
+        let text = self.text.peek();
+        let font = self.font(ctx);
+        let (scale_x, scale_y) = self.style.scale.factors();
+        let line_height = self.line_height(ctx);
+        let (width, height) = (self.rect.width as i16, self.rect.height as i16);
+        let row_bytes = self.rect.width.div_ceil(8) as usize;
+        cache.row_bytes = row_bytes;
+        cache.bitmap.clear();
+        cache
+            .bitmap
+            .resize(row_bytes * self.rect.height as usize, 0);
+        cache.rendered = true;
+
+        let block_height = self.lines(ctx, &text).count() as i16 * line_height;
+        let top = align_offset(self.style.vertical, height, block_height);
+        for (i, line) in self.lines(ctx, &text).enumerate() {
+            let line_top = top + i as i16 * line_height;
+            let mut x = align_offset(self.style.horizontal, width, line.width);
+            let ellipsis = if line.ellipsis { ELLIPSIS } else { "" };
+            for c in text[line.range].chars().chain(ellipsis.chars()) {
+                if x >= width {
+                    break;
+                }
+                let c = shown_as(c);
+                let glyph_width = min(font.glyph_width(c), MAX_GLYPH_WIDTH) as i16 * scale_x;
+                for glyph_y in 0..font.height() {
+                    let bits = font.glyph_row(c, glyph_y);
+                    if bits == 0 {
+                        continue;
+                    }
+                    let glyph_top = line_top + glyph_y as i16 * scale_y;
+                    for y in max(0, glyph_top)..min(height, glyph_top + scale_y) {
+                        let row = &mut cache.bitmap[y as usize * row_bytes..][..row_bytes];
+                        for chunk in 0..(glyph_width + 7) / 8 {
+                            or_byte(row, x + chunk * 8, stretched_byte(bits, scale_x, chunk));
+                        }
+                    }
+                }
+                x += font.advance(c) as i16 * scale_x;
+            }
         }
     }
 }
 
 impl<TO: Observable<String>> UIElement for TextUIElement<TO> {
-    fn mount_to_context(&self, ctx: &UIContext, id: usize) {
-        // TODO: Unsubscribe on unmount
+    fn mount_to_context(&self, ctx: &UIContext, id: ElementId) -> Result<(), CapacityError> {
         let els = ctx.elements_requesting_redraw.clone();
-        let subscription_id = self.text.subscribe(move |_| {
+        // Anything cached before mounting may be out of date, since nothing was listening
+        self.text_changed.set(true);
+        let text_changed = self.text_changed.clone();
+        let subscription_id = self.text.try_subscribe(move |_| {
+            text_changed.set(true);
             let mut ctx_borrowed = els.borrow_mut();
             ctx_borrowed.insert(id);
-        });
+        })?;
+        self.subscription_id.set(Some(subscription_id));
+        Ok(())
+    }
+    fn unmount_from_context(&self, _ctx: &UIContext) {
+        if let Some(subscription_id) = self.subscription_id.take() {
+            self.text.unsubscribe(subscription_id);
+        }
     }
     fn get_pixel(&self, ctx: &UIContext, x: u8, y: u8) -> u8 {
-        let text = &self.text.peek();
-        let font = &ctx.font;
-        let char_width = 8 as usize;
-        let char_height = 8 as usize;
-
-        let y = y as usize;
-        let x = x as usize;
-        if y >= char_height {
+        if x >= self.rect.width || y >= self.rect.height {
             return 0;
         }
+        let cache = self.rendered(ctx);
+        let byte = cache.bitmap[y as usize * cache.row_bytes + x as usize / 8];
+        (byte >> (7 - x % 8)) & 1
+    }
+    fn draw_row(&self, ctx: &UIContext, y: u8, span: &mut RowSpan) {
+        let (start, end) = (span.start(), span.end());
+        let cache = self.rendered(ctx);
+        let row = &cache.bitmap[y as usize * cache.row_bytes..][..cache.row_bytes];
+        let first_byte = start as usize / 8;
+        let bytes = &row[first_byte..(end as usize).div_ceil(8)];
+        // Every byte is written whole, clear pixels included - clearing first and then writing only the
+        // set ones would have the Mask composite mode wipe them
+        for (i, &bits) in bytes.iter().enumerate() {
+            span.write_byte((first_byte + i) as i16 * 8, bits);
+        }
+    }
+    fn get_bounding_rect(&self) -> BoundingRect {
+        self.rect
+    }
+    fn set_bounding_rect(&mut self, rect: BoundingRect) -> bool {
+        // Moving doesn't change how the text is drawn within the element
+        if (rect.width, rect.height) != (self.rect.width, self.rect.height) {
+            self.cache.get_mut().rendered = false;
+        }
+        self.rect = rect;
+        true
+    }
+    fn get_intrinsic_size(&self, ctx: &UIContext) -> Option<(u8, u8)> {
+        let mut cache = self.cache();
+        if cache.intrinsic_size.is_none() {
+            // Only explicit newlines break lines when there's no width to fit in
+            let size = measure_text(self.font(ctx), &self.text.peek(), self.style, None);
+            cache.intrinsic_size = Some((
+                size.width.clamp(0, u8::MAX as i16) as u8,
+                size.height.clamp(0, u8::MAX as i16) as u8,
+            ));
+        }
+        cache.intrinsic_size
+    }
+    fn get_fits_content(&self) -> bool {
+        self.fits_content
+    }
+    fn set_fits_content(&mut self, fits_content: bool) -> bool {
+        self.fits_content = fits_content;
+        true
+    }
+    fn set_text_style(&mut self, style: TextStyle) -> bool {
+        self.style = style;
+        self.cache.get_mut().clear();
+        true
+    }
+    fn set_font(&mut self, font: &'static dyn Font) -> bool {
+        self.font = Some(font);
+        self.cache.get_mut().clear();
+        true
+    }
+    fn get_layout_sizing(&self) -> Sizing {
+        self.layout_sizing
+    }
+    fn set_layout_sizing(&mut self, sizing: Sizing) -> bool {
+        self.layout_sizing = sizing;
+        true
+    }
+    fn get_first_child_id(&self) -> Option<ElementId> {
+        None
+    }
+    fn get_next_element_id(&self) -> Option<ElementId> {
+        self.next_element_id
+    }
+    fn set_next_element_id(&mut self, id: Option<ElementId>) {
+        self.next_element_id = id
+    }
+    fn insert_child_at_end(&mut self, _ctx: &mut UIContext, _id: ElementId) {
+        panic!("TextUIElement does not support children");
+    }
+    fn get_z_index(&self) -> i8 {
+        self.z_index
+    }
+    fn set_z_index(&mut self, z_index: i8) -> bool {
+        self.z_index = z_index;
+        true
+    }
+    fn get_composite_mode(&self) -> CompositeMode {
+        self.composite_mode
+    }
+    fn set_composite_mode(&mut self, mode: CompositeMode) -> bool {
+        self.composite_mode = mode;
+        true
+    }
+}
 
-        let char_idx = x / char_width;
-        let col = x % char_width;
-        let row = y % char_height;
+// Draws a bitmap at its top left. Whatever of the rect the bitmap doesn't cover is transparent, as are
+// pixels the bitmap's mask leaves out.
+pub struct ImageUIElement {
+    image: Bitmap<'static>,
+    rect: BoundingRect,
+    fits_content: bool,
+    next_element_id: Option<ElementId>,
+    z_index: i8,
+    composite_mode: CompositeMode,
+    layout_sizing: Sizing,
+}
 
-        let c_option = text.chars().nth(char_idx);
-        if let Some(c) = c_option {
-            let glyph = font.get(c).unwrap_or_default();
-            let row_bits = glyph[row].reverse_bits();
-            if (row_bits & (1 << (7 - col))) != 0 {
-                1
-            } else {
-                0
-            }
+impl ImageUIElement {
+    // Starts out the size of the image
+    pub fn new(image: Bitmap<'static>, x: i16, y: i16) -> ImageUIElement {
+        assert!(image.is_complete(), "bitmap is missing rows");
+        ImageUIElement {
+            image,
+            rect: BoundingRect {
+                x,
+                y,
+                width: image.width,
+                height: image.height,
+            },
+            fits_content: false,
+            next_element_id: None,
+            z_index: 0,
+            composite_mode: CompositeMode::Normal,
+            layout_sizing: Sizing::default(),
+        }
+    }
+}
+
+impl UIElement for ImageUIElement {
+    fn mount_to_context(&self, _ctx: &UIContext, _id: ElementId) -> Result<(), CapacityError> {
+        Ok(())
+    }
+    fn get_pixel(&self, _ctx: &UIContext, x: u8, y: u8) -> u8 {
+        self.image.get_pixel(x, y)
+    }
+    fn draw_row(&self, _ctx: &UIContext, y: u8, span: &mut RowSpan) {
+        let image = &self.image;
+        let end = min(span.end(), image.width);
+        if y >= image.height || span.start() >= end {
+            return;
+        }
+        let row_start = y as usize * image.row_bytes();
+        for byte in span.start() as usize / 8..(end as usize).div_ceil(8) {
+            let x = byte * 8;
+            // The padding bits past the right edge aren't part of the image
+            let in_image = (0xFF00u16 >> min(8, image.width as usize - x)) as u8;
+            let opaque = image.mask.map_or(0xFF, |mask| mask[row_start + byte]);
+            span.write_byte_masked(x as i16, image.data[row_start + byte], opaque & in_image);
+        }
+    }
+    fn get_bounding_rect(&self) -> BoundingRect {
+        self.rect
+    }
+    fn set_bounding_rect(&mut self, rect: BoundingRect) -> bool {
+        self.rect = rect;
+        true
+    }
+    fn set_image(&mut self, image: Bitmap<'static>) -> bool {
+        assert!(image.is_complete(), "bitmap is missing rows");
+        self.image = image;
+        true
+    }
+    fn get_intrinsic_size(&self, _ctx: &UIContext) -> Option<(u8, u8)> {
+        Some((self.image.width, self.image.height))
+    }
+    fn get_fits_content(&self) -> bool {
+        self.fits_content
+    }
+    fn set_fits_content(&mut self, fits_content: bool) -> bool {
+        self.fits_content = fits_content;
+        true
+    }
+    fn get_layout_sizing(&self) -> Sizing {
+        self.layout_sizing
+    }
+    fn set_layout_sizing(&mut self, sizing: Sizing) -> bool {
+        self.layout_sizing = sizing;
+        true
+    }
+    fn get_first_child_id(&self) -> Option<ElementId> {
+        None
+    }
+    fn get_next_element_id(&self) -> Option<ElementId> {
+        self.next_element_id
+    }
+    fn set_next_element_id(&mut self, id: Option<ElementId>) {
+        self.next_element_id = id
+    }
+    fn insert_child_at_end(&mut self, _ctx: &mut UIContext, _id: ElementId) {
+        panic!("ImageUIElement does not support children");
+    }
+    fn get_z_index(&self) -> i8 {
+        self.z_index
+    }
+    fn set_z_index(&mut self, z_index: i8) -> bool {
+        self.z_index = z_index;
+        true
+    }
+    fn get_composite_mode(&self) -> CompositeMode {
+        self.composite_mode
+    }
+    fn set_composite_mode(&mut self, mode: CompositeMode) -> bool {
+        self.composite_mode = mode;
+        true
+    }
+}
+
+// Draws a Shape. It starts out with the rect around the shape, and moving the element moves the shape with
+// it. Resizing crops the shape, apart from rounded rects, which fill the element.
+pub struct ShapeUIElement {
+    shape: Shape,
+    style: ShapeStyle,
+    rect: BoundingRect,
+    // Drawn for the rect's size
+    bits: ShapeBits,
+    next_element_id: Option<ElementId>,
+    z_index: i8,
+    composite_mode: CompositeMode,
+    layout_sizing: Sizing,
+}
+
+impl ShapeUIElement {
+    pub fn new(shape: Shape, style: ShapeStyle) -> ShapeUIElement {
+        let mut element = ShapeUIElement {
+            rect: shape.bounds(style.stroke_width),
+            shape,
+            style,
+            bits: ShapeBits::default(),
+            next_element_id: None,
+            z_index: 0,
+            composite_mode: CompositeMode::Normal,
+            layout_sizing: Sizing::default(),
+        };
+        element.render();
+        element
+    }
+    // Where the shape is drawn from, in the shape's own coordinates - the element may have been moved since
+    fn origin(&self) -> Point {
+        let bounds = self.shape.bounds(self.style.stroke_width);
+        Point::new(bounds.x, bounds.y)
+    }
+    fn render(&mut self) {
+        let BoundingRect { width, height, .. } = self.rect;
+        self.bits.render(
+            &self.shape,
+            self.style.stroke_width,
+            self.origin(),
+            width,
+            height,
+        );
+    }
+    // Swaps what's drawn while keeping however far the element has been moved
+    fn reshape(&mut self, shape: Shape, style: ShapeStyle) {
+        let old = self.origin();
+        self.shape = shape;
+        self.style = style;
+        let bounds = self.shape.bounds(style.stroke_width);
+        self.rect = BoundingRect {
+            x: self.rect.x + bounds.x - old.x,
+            y: self.rect.y + bounds.y - old.y,
+            ..bounds
+        };
+        self.render();
+    }
+    // The stroke and fill pixels of a row byte that are shown, and which of them are set
+    fn byte(&self, index: usize) -> (u8, u8) {
+        let stroke = if self.style.stroke == TRANSPARENT {
+            0
         } else {
+            self.bits.stroke[index]
+        };
+        let fill = if self.style.fill == TRANSPARENT {
             0
+        } else {
+            self.bits.fill[index] & !stroke
+        };
+        let set = |color: u8, pixels: u8| if color == 1 { pixels } else { 0 };
+        (
+            stroke | fill,
+            set(self.style.stroke, stroke) | set(self.style.fill, fill),
+        )
+    }
+}
+
+impl UIElement for ShapeUIElement {
+    fn mount_to_context(&self, _ctx: &UIContext, _id: ElementId) -> Result<(), CapacityError> {
+        Ok(())
+    }
+    fn get_pixel(&self, _ctx: &UIContext, x: u8, y: u8) -> u8 {
+        if x >= self.rect.width || y >= self.rect.height {
+            return TRANSPARENT;
+        }
+        let (opaque, bits) = self.byte(y as usize * self.bits.row_bytes + x as usize / 8);
+        let bit = 0x80 >> (x % 8);
+        match (opaque & bit, bits & bit) {
+            (0, _) => TRANSPARENT,
+            (_, 0) => 0,
+            _ => 1,
+        }
+    }
+    fn draw_row(&self, _ctx: &UIContext, y: u8, span: &mut RowSpan) {
+        let row_start = y as usize * self.bits.row_bytes;
+        for byte in span.start() as usize / 8..(span.end() as usize).div_ceil(8) {
+            let (opaque, bits) = self.byte(row_start + byte);
+            if opaque != 0 {
+                span.write_byte_masked(byte as i16 * 8, bits, opaque);
+            }
         }
     }
     fn get_bounding_rect(&self) -> BoundingRect {
         self.rect
     }
-    fn get_first_child_id(&self) -> usize {
-        0
+    fn set_bounding_rect(&mut self, rect: BoundingRect) -> bool {
+        let resized = (rect.width, rect.height) != (self.rect.width, self.rect.height);
+        self.rect = rect;
+        if resized {
+            self.render();
+        }
+        true
+    }
+    fn set_shape(&mut self, shape: Shape) -> bool {
+        self.reshape(shape, self.style);
+        true
+    }
+    fn set_shape_style(&mut self, style: ShapeStyle) -> bool {
+        self.reshape(self.shape.clone(), style);
+        true
+    }
+    fn get_intrinsic_size(&self, _ctx: &UIContext) -> Option<(u8, u8)> {
+        let bounds = self.shape.bounds(self.style.stroke_width);
+        Some((bounds.width, bounds.height))
     }
-    fn get_next_element_id(&self) -> usize {
+    fn get_layout_sizing(&self) -> Sizing {
+        self.layout_sizing
+    }
+    fn set_layout_sizing(&mut self, sizing: Sizing) -> bool {
+        self.layout_sizing = sizing;
+        true
+    }
+    fn get_first_child_id(&self) -> Option<ElementId> {
+        None
+    }
+    fn get_next_element_id(&self) -> Option<ElementId> {
         self.next_element_id
     }
-    fn set_next_element_id(&mut self, id: usize) {
+    fn set_next_element_id(&mut self, id: Option<ElementId>) {
         self.next_element_id = id
     }
-    fn insert_child_at_end(&mut self, ctx: &mut UIContext, id: usize) {
-        panic!("TextUIElement does not support children");
+    fn insert_child_at_end(&mut self, _ctx: &mut UIContext, _id: ElementId) {
+        panic!("ShapeUIElement does not support children");
+    }
+    fn get_z_index(&self) -> i8 {
+        self.z_index
+    }
+    fn set_z_index(&mut self, z_index: i8) -> bool {
+        self.z_index = z_index;
+        true
+    }
+    fn get_composite_mode(&self) -> CompositeMode {
+        self.composite_mode
+    }
+    fn set_composite_mode(&mut self, mode: CompositeMode) -> bool {
+        self.composite_mode = mode;
+        true
     }
 }
 
 pub struct RectUIElement {
     rect: BoundingRect,
     color: u8,
-    next_element_id: usize,
-    first_child_id: usize,
+    next_element_id: Option<ElementId>,
+    first_child_id: Option<ElementId>,
+    z_index: i8,
+    composite_mode: CompositeMode,
+    clips_children: bool,
+    layout: Option<Layout>,
+    layout_sizing: Sizing,
 }
 
 impl RectUIElement {
@@ -495,49 +2189,116 @@ impl RectUIElement {
         RectUIElement {
             rect,
             color,
-            next_element_id: 0,
-            first_child_id: 0,
+            next_element_id: None,
+            first_child_id: None,
+            z_index: 0,
+            composite_mode: CompositeMode::Normal,
+            clips_children: false,
+            layout: None,
+            layout_sizing: Sizing::default(),
         }
     }
 }
 
 impl UIElement for RectUIElement {
-    fn mount_to_context(&self, _ctx: &UIContext, _id: usize) {}
+    fn mount_to_context(&self, _ctx: &UIContext, _id: ElementId) -> Result<(), CapacityError> {
+        Ok(())
+    }
     fn get_pixel(&self, _ctx: &UIContext, _x: u8, _y: u8) -> u8 {
         self.color
     }
+    fn draw_row(&self, _ctx: &UIContext, _y: u8, span: &mut RowSpan) {
+        span.fill(span.start(), span.end(), self.color);
+    }
     fn get_bounding_rect(&self) -> BoundingRect {
         self.rect
     }
-    fn get_first_child_id(&self) -> usize {
+    fn set_bounding_rect(&mut self, rect: BoundingRect) -> bool {
+        self.rect = rect;
+        true
+    }
+    fn set_color(&mut self, color: u8) -> bool {
+        self.color = color;
+        true
+    }
+    fn supports_children(&self) -> bool {
+        true
+    }
+    fn get_first_child_id(&self) -> Option<ElementId> {
         self.first_child_id
     }
-    fn get_next_element_id(&self) -> usize {
+    fn set_first_child_id(&mut self, id: Option<ElementId>) {
+        self.first_child_id = id;
+    }
+    fn get_next_element_id(&self) -> Option<ElementId> {
         self.next_element_id
     }
-    fn set_next_element_id(&mut self, id: usize) {
+    fn set_next_element_id(&mut self, id: Option<ElementId>) {
         self.next_element_id = id
     }
-    fn insert_child_at_end(&mut self, ui_context: &mut UIContext, element_id: usize) {
-        if self.first_child_id == 0 {
-            self.first_child_id = element_id;
+    fn insert_child_at_end(&mut self, ui_context: &mut UIContext, element_id: ElementId) {
+        let Some(mut curr_child_id) = self.first_child_id else {
+            self.first_child_id = Some(element_id);
             return;
-        }
-        let mut curr_child_id = self.first_child_id;
-        loop {
-            if curr_child_id == 0 {
-                break;
-            }
-            curr_child_id = ui_context
-                .elements
-                .get(curr_child_id)
-                .unwrap()
-                .get_next_element_id();
+        };
+        while let Some(next_id) = ui_context
+            .elements
+            .get(curr_child_id.0)
+            .unwrap()
+            .get_next_element_id()
+        {
+            curr_child_id = next_id;
         }
         ui_context
             .elements
-            .get_mut(curr_child_id)
+            .get_mut(curr_child_id.0)
             .unwrap()
-            .set_next_element_id(element_id);
+            .set_next_element_id(Some(element_id));
+    }
+    fn get_z_index(&self) -> i8 {
+        self.z_index
+    }
+    fn set_z_index(&mut self, z_index: i8) -> bool {
+        self.z_index = z_index;
+        true
+    }
+    fn get_composite_mode(&self) -> CompositeMode {
+        self.composite_mode
+    }
+    fn set_composite_mode(&mut self, mode: CompositeMode) -> bool {
+        self.composite_mode = mode;
+        true
+    }
+    fn get_clips_children(&self) -> bool {
+        self.clips_children
+    }
+    fn set_clips_children(&mut self, clips_children: bool) -> bool {
+        self.clips_children = clips_children;
+        true
+    }
+    fn get_intrinsic_size(&self, ctx: &UIContext) -> Option<(u8, u8)> {
+        // Containers fit their content - plain rects are whatever size they were given
+        let layout = self.layout?;
+        let children = core::iter::successors(self.first_child_id, |id| {
+            ctx.elements.get(id.0).unwrap().get_next_element_id()
+        });
+        let items = children
+            .filter(|id| !ctx.hidden_elements.contains(id))
+            .map(|id| ctx.layout_item(id));
+        Some(measure(&layout, items))
+    }
+    fn get_layout_sizing(&self) -> Sizing {
+        self.layout_sizing
+    }
+    fn set_layout_sizing(&mut self, sizing: Sizing) -> bool {
+        self.layout_sizing = sizing;
+        true
+    }
+    fn get_layout(&self) -> Option<Layout> {
+        self.layout
+    }
+    fn set_layout(&mut self, layout: Option<Layout>) -> bool {
+        self.layout = layout;
+        true
     }
 }
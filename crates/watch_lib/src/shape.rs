@@ -0,0 +1,474 @@
+use crate::{BoundingRect, TRANSPARENT};
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Point {
+    pub x: i16,
+    pub y: i16,
+}
+
+impl Point {
+    pub fn new(x: i16, y: i16) -> Point {
+        Point { x, y }
+    }
+}
+
+// Points are pixels, relative to the parent like a BoundingRect. Angles are in degrees, clockwise from
+// 12 o'clock like a watch hand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Line {
+        from: Point,
+        to: Point,
+    },
+    Circle {
+        center: Point,
+        radius: u8,
+    },
+    // From start round to end. Filling it makes a pie slice.
+    Arc {
+        center: Point,
+        radius: u8,
+        start: i16,
+        end: i16,
+    },
+    // Closed, filled even-odd
+    Polygon(Vec<Point>),
+    // Stroked inside the rect rather than centered on its edge
+    RoundedRect {
+        rect: BoundingRect,
+        radius: u8,
+    },
+}
+
+// Colors are 0, 1 or TRANSPARENT to leave that part out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShapeStyle {
+    pub stroke: u8,
+    pub stroke_width: u8,
+    pub fill: u8,
+}
+
+impl Default for ShapeStyle {
+    fn default() -> ShapeStyle {
+        ShapeStyle {
+            stroke: 1,
+            stroke_width: 1,
+            fill: TRANSPARENT,
+        }
+    }
+}
+
+// sin(0..=90 degrees), scaled by 1 << 14
+#[rustfmt::skip]
+const SIN: [i32; 91] = [
+        0,   286,   572,   857,  1143,  1428,  1713,  1997,  2280,  2563,
+     2845,  3126,  3406,  3686,  3964,  4240,  4516,  4790,  5063,  5334,
+     5604,  5872,  6138,  6402,  6664,  6924,  7182,  7438,  7692,  7943,
+     8192,  8438,  8682,  8923,  9162,  9397,  9630,  9860, 10087, 10311,
+    10531, 10749, 10963, 11174, 11381, 11585, 11786, 11982, 12176, 12365,
+    12551, 12733, 12911, 13085, 13255, 13421, 13583, 13741, 13894, 14044,
+    14189, 14330, 14466, 14598, 14726, 14849, 14968, 15082, 15191, 15296,
+    15396, 15491, 15582, 15668, 15749, 15826, 15897, 15964, 16026, 16083,
+    16135, 16182, 16225, 16262, 16294, 16322, 16344, 16362, 16374, 16382,
+    16384,
+];
+
+fn sin(degrees: i32) -> i32 {
+    let degrees = degrees.rem_euclid(360) as usize;
+    match degrees {
+        0..=90 => SIN[degrees],
+        91..=180 => SIN[180 - degrees],
+        181..=270 => -SIN[degrees - 180],
+        _ => -SIN[360 - degrees],
+    }
+}
+
+// Which way a hand at this angle points, scaled by 1 << 14, with y going down the screen
+fn direction(degrees: i16) -> (i32, i32) {
+    (sin(degrees as i32), -sin(degrees as i32 + 90))
+}
+
+// Where a hand or tick mark of this length ends, for building analog faces out of lines
+pub fn point_on_circle(center: Point, radius: i16, degrees: i16) -> Point {
+    let (dx, dy) = direction(degrees);
+    // Rounded to the nearest pixel
+    let scaled = |d: i32| ((radius as i32 * d + (1 << 13)) >> 14) as i16;
+    Point::new(center.x + scaled(dx), center.y + scaled(dy))
+}
+
+// How far a stroke reaches either side of the pixel it's centered on
+fn brush_reach(width: u8) -> (i16, i16) {
+    (-((width as i16 - 1) / 2), width as i16 / 2)
+}
+
+impl Shape {
+    // The pixels the shape covers, stroke included
+    pub fn bounds(&self, stroke_width: u8) -> BoundingRect {
+        let (lo, hi) = brush_reach(stroke_width);
+        let (x0, y0, x1, y1) = match self {
+            Shape::Line { from, to } => (
+                min(from.x, to.x),
+                min(from.y, to.y),
+                max(from.x, to.x),
+                max(from.y, to.y),
+            ),
+            Shape::Circle { center, radius } | Shape::Arc { center, radius, .. } => {
+                let radius = *radius as i16;
+                (
+                    center.x - radius,
+                    center.y - radius,
+                    center.x + radius,
+                    center.y + radius,
+                )
+            }
+            Shape::Polygon(points) if points.is_empty() => return BoundingRect::default(),
+            Shape::Polygon(points) => points.iter().fold(
+                (i16::MAX, i16::MAX, i16::MIN, i16::MIN),
+                |(x0, y0, x1, y1), p| (min(x0, p.x), min(y0, p.y), max(x1, p.x), max(y1, p.y)),
+            ),
+            Shape::RoundedRect { rect, .. } => return *rect,
+        };
+        let size =
+            |from: i16, to: i16| (to as i32 - from as i32 + 1).clamp(0, u8::MAX as i32) as u8;
+        BoundingRect {
+            x: x0 + lo,
+            y: y0 + lo,
+            width: size(x0 + lo, x1 + hi),
+            height: size(y0 + lo, y1 + hi),
+        }
+    }
+}
+
+// A shape's stroke and fill pixels, packed like the screen buffer. These are on the heap even with
+// fixed-capacity. Rendering allocates them the first time, and again only if the shape has grown.
+#[derive(Default)]
+pub(crate) struct ShapeBits {
+    pub(crate) row_bytes: usize,
+    pub(crate) stroke: Vec<u8>,
+    pub(crate) fill: Vec<u8>,
+}
+
+impl ShapeBits {
+    // Draws the part of the shape inside a width by height area starting at origin, in the shape's
+    // coordinates, replacing whatever was drawn before
+    pub(crate) fn render(
+        &mut self,
+        shape: &Shape,
+        stroke_width: u8,
+        origin: Point,
+        width: u8,
+        height: u8,
+    ) {
+        let row_bytes = width.div_ceil(8) as usize;
+        self.row_bytes = row_bytes;
+        for plane in [&mut self.stroke, &mut self.fill] {
+            plane.clear();
+            plane.resize(row_bytes * height as usize, 0);
+        }
+        let bits = self;
+        let area = Area {
+            row_bytes,
+            width: width as i16,
+            height: height as i16,
+            origin,
+        };
+        let mut stroke = area.plane(&mut bits.stroke);
+        let brush = |stroke: &mut Plane, p: Point| {
+            if stroke_width > 0 {
+                stroke.brush(p, stroke_width);
+            }
+        };
+        match shape {
+            Shape::Line { from, to } => line(*from, *to, |p| brush(&mut stroke, p)),
+            Shape::Circle { center, radius } => {
+                octant(*radius as i16, |dx, dy| {
+                    for (dx, dy) in mirrored(dx, dy) {
+                        brush(&mut stroke, Point::new(center.x + dx, center.y + dy));
+                    }
+                });
+                let extents = disc_extents(*radius as i16);
+                let mut fill = area.plane(&mut bits.fill);
+                for dy in -(*radius as i16)..=*radius as i16 {
+                    let extent = extents[dy.unsigned_abs() as usize];
+                    fill.span(center.x - extent, center.x + extent, center.y + dy);
+                }
+            }
+            Shape::Arc {
+                center,
+                radius,
+                start,
+                end,
+            } => {
+                let sweep = Sweep::new(*start, *end);
+                octant(*radius as i16, |dx, dy| {
+                    for (dx, dy) in mirrored(dx, dy) {
+                        if sweep.contains(dx, dy) {
+                            brush(&mut stroke, Point::new(center.x + dx, center.y + dy));
+                        }
+                    }
+                });
+                let extents = disc_extents(*radius as i16);
+                let mut fill = area.plane(&mut bits.fill);
+                for dy in -(*radius as i16)..=*radius as i16 {
+                    let extent = extents[dy.unsigned_abs() as usize];
+                    for dx in -extent..=extent {
+                        if sweep.contains(dx, dy) {
+                            fill.span(center.x + dx, center.x + dx, center.y + dy);
+                        }
+                    }
+                }
+            }
+            Shape::Polygon(points) => {
+                for (i, &from) in points.iter().enumerate() {
+                    let to = points[(i + 1) % points.len()];
+                    line(from, to, |p| brush(&mut stroke, p));
+                }
+                fill_polygon(&mut area.plane(&mut bits.fill), points);
+            }
+            Shape::RoundedRect { rect, radius } => {
+                // Whatever size the element is now, rather than the rect it started as
+                let (x0, y0) = (rect.x, rect.y);
+                let (x1, y1) = (x0 + width as i16 - 1, y0 + height as i16 - 1);
+                let radius = *radius as i16;
+                rounded_rect(&mut stroke, x0, y0, x1, y1, radius);
+                // The inside of the stroke goes in the fill plane for now
+                let inset = stroke_width as i16;
+                rounded_rect(
+                    &mut area.plane(&mut bits.fill),
+                    x0 + inset,
+                    y0 + inset,
+                    x1 - inset,
+                    y1 - inset,
+                    max(radius - inset, 0),
+                );
+                // The fill goes under the stroke, so it can cover the whole rect
+                for (stroke, fill) in bits.stroke.iter_mut().zip(bits.fill.iter_mut()) {
+                    let (outer, inner) = (*stroke, *fill);
+                    *stroke = outer & !inner;
+                    *fill = outer;
+                }
+            }
+        }
+    }
+}
+
+// The part of the shape being drawn
+#[derive(Clone, Copy)]
+struct Area {
+    row_bytes: usize,
+    width: i16,
+    height: i16,
+    origin: Point,
+}
+
+impl Area {
+    fn plane<'a>(&self, bits: &'a mut [u8]) -> Plane<'a> {
+        Plane { bits, area: *self }
+    }
+}
+
+// One of the bit planes being drawn into
+struct Plane<'a> {
+    bits: &'a mut [u8],
+    area: Area,
+}
+
+impl Plane<'_> {
+    // Sets x0..=x1 of row y, clipped to the plane
+    fn span(&mut self, x0: i16, x1: i16, y: i16) {
+        let Area {
+            row_bytes,
+            width,
+            height,
+            origin,
+        } = self.area;
+        let y = y - origin.y;
+        if y < 0 || y >= height {
+            return;
+        }
+        let x0 = max(x0 - origin.x, 0);
+        let x1 = min(x1 - origin.x, width - 1);
+        let row = &mut self.bits[y as usize * row_bytes..][..row_bytes];
+        for x in x0..=x1 {
+            row[x as usize / 8] |= 0x80 >> (x % 8);
+        }
+    }
+
+    // Flips x0 to the right edge of row y, clipped to the plane
+    fn flip_from(&mut self, x0: i16, y: i16) {
+        let Area {
+            row_bytes,
+            width,
+            height,
+            origin,
+        } = self.area;
+        let y = y - origin.y;
+        if y < 0 || y >= height {
+            return;
+        }
+        let row = &mut self.bits[y as usize * row_bytes..][..row_bytes];
+        for x in max(x0 - origin.x, 0)..width {
+            row[x as usize / 8] ^= 0x80 >> (x % 8);
+        }
+    }
+
+    // A round dot width pixels across. Up to 3 wide it's square.
+    fn brush(&mut self, p: Point, width: u8) {
+        let (lo, hi) = brush_reach(width);
+        let center = (lo + hi) as i32;
+        let inside = |dx: i16, dy: i16| {
+            let (x, y) = (2 * dx as i32 - center, 2 * dy as i32 - center);
+            x * x + y * y <= width as i32 * width as i32
+        };
+        for dy in lo..=hi {
+            if let Some(left) = (lo..=hi).find(|&dx| inside(dx, dy)) {
+                self.span(p.x + left, p.x + lo + hi - left, p.y + dy);
+            }
+        }
+    }
+}
+
+// Bresenham's line, including both ends
+fn line(from: Point, to: Point, mut plot: impl FnMut(Point)) {
+    let (dx, dy) = (
+        (to.x as i32 - from.x as i32).abs(),
+        -(to.y as i32 - from.y as i32).abs(),
+    );
+    let (step_x, step_y) = (
+        if from.x < to.x { 1 } else { -1 },
+        if from.y < to.y { 1 } else { -1 },
+    );
+    let mut error = dx + dy;
+    let mut p = from;
+    loop {
+        plot(p);
+        if p == to {
+            return;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            p.x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            p.y += step_y;
+        }
+    }
+}
+
+// The midpoint circle's pixels in one octant, as offsets from the center with dx >= dy >= 0
+fn octant(radius: i16, mut plot: impl FnMut(i16, i16)) {
+    let (mut x, mut y, mut error) = (radius, 0, 1 - radius);
+    while x >= y {
+        plot(x, y);
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+fn mirrored(dx: i16, dy: i16) -> [(i16, i16); 8] {
+    [
+        (dx, dy),
+        (dy, dx),
+        (-dy, dx),
+        (-dx, dy),
+        (-dx, -dy),
+        (-dy, -dx),
+        (dy, -dx),
+        (dx, -dy),
+    ]
+}
+
+// How far a filled circle reaches either side of its center, on each row away from it
+fn disc_extents(radius: i16) -> [i16; 256] {
+    let mut extents = [0; 256];
+    octant(radius, |dx, dy| {
+        extents[dy as usize] = max(extents[dy as usize], dx);
+        extents[dx as usize] = max(extents[dx as usize], dy);
+    });
+    extents
+}
+
+// The directions from start clockwise round to end
+struct Sweep {
+    from: (i32, i32),
+    to: (i32, i32),
+    degrees: i32,
+}
+
+impl Sweep {
+    fn new(start: i16, end: i16) -> Sweep {
+        let turn = end as i32 - start as i32;
+        Sweep {
+            from: direction(start),
+            to: direction(end),
+            degrees: if turn.abs() >= 360 {
+                360
+            } else {
+                turn.rem_euclid(360)
+            },
+        }
+    }
+
+    fn contains(&self, dx: i16, dy: i16) -> bool {
+        // Positive when b is clockwise of a, less than half a turn round
+        let clockwise = |a: (i32, i32), b: (i32, i32)| a.0 * b.1 - a.1 * b.0;
+        let p = (dx as i32, dy as i32);
+        match self.degrees {
+            0 => false,
+            360 => true,
+            1..=180 => clockwise(self.from, p) >= 0 && clockwise(p, self.to) >= 0,
+            // Everything but the sweep the other way round
+            _ => !(clockwise(self.to, p) > 0 && clockwise(p, self.from) > 0),
+        }
+    }
+}
+
+// Fills the pixels whose top left corners are inside the polygon, so shapes sharing an edge don't overlap.
+// Each edge crossing a row flips everything right of it, which leaves the pixels between the first and
+// second crossings set, the third and fourth, and so on, without having to sort them.
+fn fill_polygon(plane: &mut Plane, points: &[Point]) {
+    let Some(top) = points.iter().map(|p| p.y).min() else {
+        return;
+    };
+    let bottom = points.iter().map(|p| p.y).max().unwrap();
+    for y in top..=bottom {
+        for (i, from) in points.iter().enumerate() {
+            let to = points[(i + 1) % points.len()];
+            if (from.y <= y) != (to.y <= y) {
+                // Where the edge crosses the row, in 1/256ths of a pixel. In i64, since points far off the
+                // screen would overflow an i32.
+                let along = (y as i64 - from.y as i64) * (to.x as i64 - from.x as i64) * 256;
+                let crossing = from.x as i64 * 256 + along / (to.y as i64 - from.y as i64);
+                // The first pixel starting at or after it
+                plane.flip_from(-(-crossing).div_euclid(256) as i16, y);
+            }
+        }
+    }
+}
+
+// x0..=x1 by y0..=y1 with quarter circle corners
+fn rounded_rect(plane: &mut Plane, x0: i16, y0: i16, x1: i16, y1: i16, radius: i16) {
+    if x1 < x0 || y1 < y0 {
+        return;
+    }
+    let radius = min(radius, min(x1 - x0 + 1, y1 - y0 + 1) / 2);
+    let extents = disc_extents(radius);
+    for y in y0..=y1 {
+        let from_corner = max(y0 + radius - y, y - (y1 - radius));
+        let inset = if from_corner > 0 {
+            radius - extents[from_corner as usize]
+        } else {
+            0
+        };
+        plane.span(x0 + inset, x1 - inset, y);
+    }
+}
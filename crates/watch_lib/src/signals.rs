@@ -1,11 +1,32 @@
+use crate::{ArbitraryIdStore, CapacityError, SlotKey};
 use alloc::rc::Rc;
-use alloc::vec::Vec;
 use core::cell::RefCell;
 
+// In fixed-capacity builds this is the most listeners a single signal can have
+pub const MAX_LISTENERS: usize = 4;
+
+// Closures differ in size, so subscribing still allocates in fixed-capacity builds - it's only the slots
+// holding the listeners that are fixed
+type Listener<T> = Rc<RefCell<dyn FnMut(T)>>;
+type Listeners<T> = ArbitraryIdStore<Listener<T>, MAX_LISTENERS>;
+
+// Handle to a listener, for unsubscribing. Unsubscribing twice is harmless - the second call is ignored
+// rather than removing whichever listener took over the slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(SlotKey);
+
+fn add_listener<T, F: FnMut(T) + 'static>(
+    listeners: &mut Listeners<T>,
+    on_change: F,
+) -> Result<SubscriptionId, CapacityError> {
+    let listener: Listener<T> = Rc::new(RefCell::new(on_change));
+    listeners.add(listener).map(SubscriptionId)
+}
+
 // TODO: safely handle unwraps
 struct SignalData<T: Clone> {
     value: T,
-    listeners: Vec<Option<Rc<RefCell<dyn FnMut(T)>>>>,
+    listeners: Listeners<T>,
 }
 
 // Signals are just "lightweight" containers of pointers - should be safe and cheap to clone.
@@ -23,17 +44,15 @@ impl<T: Clone + PartialEq> Signal<T> {
         }
         let mut data = self.data.borrow_mut();
         data.value = value;
-        for i in 0..data.listeners.len() {
-            if let Some(listener) = &data.listeners[i] {
-                (listener.borrow_mut())(data.value.clone());
-            }
+        for listener in data.listeners.values() {
+            (listener.borrow_mut())(data.value.clone());
         }
     }
     pub fn new(initial_value: T) -> Signal<T> {
         Signal {
             data: Rc::new(RefCell::new(SignalData {
                 value: initial_value,
-                listeners: Vec::with_capacity(4),
+                listeners: ArbitraryIdStore::new(),
             })),
         }
     }
@@ -41,37 +60,34 @@ impl<T: Clone + PartialEq> Signal<T> {
 
 pub trait Observable<T>: Clone {
     fn peek(&self) -> T;
-    fn subscribe<F: FnMut(T) + 'static>(&self, on_change: F) -> usize;
-    fn unsubscribe(&self, id: usize);
+    // Fails if the observable is out of listener slots in a fixed-capacity build
+    fn try_subscribe<F: FnMut(T) + 'static>(
+        &self,
+        on_change: F,
+    ) -> Result<SubscriptionId, CapacityError>;
+    fn subscribe<F: FnMut(T) + 'static>(&self, on_change: F) -> SubscriptionId {
+        self.try_subscribe(on_change)
+            .expect("no room left for another listener")
+    }
+    fn unsubscribe(&self, id: SubscriptionId);
 }
 
 impl<T: Clone> Observable<T> for Signal<T> {
     fn peek(&self) -> T {
         self.data.borrow().value.clone()
     }
-    fn subscribe<F: FnMut(T) + 'static>(&self, on_change: F) -> usize {
-        let mut data = self.data.borrow_mut();
-        // TODO: is there a more performant way to fill old gaps? remember, index matters - maybe this should be a hashmap instead
-        let chosen_index = data
-            .listeners
-            .iter()
-            .enumerate()
-            .rev()
-            .find(|(_, v)| v.is_none())
-            .map(|i| i.0);
-        if let Some(i) = chosen_index {
-            data.listeners[i] = Some(Rc::new(RefCell::new(on_change)));
-        } else {
-            data.listeners.push(Some(Rc::new(RefCell::new(on_change))));
-        }
-        data.listeners.len() - 1
+    fn try_subscribe<F: FnMut(T) + 'static>(
+        &self,
+        on_change: F,
+    ) -> Result<SubscriptionId, CapacityError> {
+        add_listener(&mut self.data.borrow_mut().listeners, on_change)
     }
-    fn unsubscribe(&self, id: usize) {
-        let mut data = self.data.borrow_mut();
-        data.listeners[id] = None;
+    fn unsubscribe(&self, id: SubscriptionId) {
+        self.data.borrow_mut().listeners.delete(id.0);
     }
 }
 
+// Panics if dep0 is out of listener slots in a fixed-capacity build
 pub fn derived<
     Computation: Clone + PartialEq + 'static,
     Compute: Fn(D0) -> Computation + 'static,
@@ -81,23 +97,36 @@ pub fn derived<
     dep0: &OD0,
     compute: Compute,
 ) -> DerivedSignal<Computation, D0, Compute> {
+    try_derived(dep0, compute).expect("no room left for another listener")
+}
+
+pub fn try_derived<
+    Computation: Clone + PartialEq + 'static,
+    Compute: Fn(D0) -> Computation + 'static,
+    D0: Clone + 'static,
+    OD0: Observable<D0>,
+>(
+    dep0: &OD0,
+    compute: Compute,
+) -> Result<DerivedSignal<Computation, D0, Compute>, CapacityError> {
     let ds = DerivedSignal {
         data: Rc::new(RefCell::new(DerivedSignalData {
             cache: None,
             deps: dep0.peek(),
-            compute: compute,
-            listeners: Vec::with_capacity(4),
+            compute,
+            listeners: ArbitraryIdStore::new(),
         })),
     };
     let ds_clone = ds.data.clone();
-    dep0.subscribe(move |new| {
+    dep0.try_subscribe(move |new| {
         let mut borrowed = ds_clone.borrow_mut();
         borrowed.deps = new;
         borrowed.maybe_recompute();
-    });
-    ds
+    })?;
+    Ok(ds)
 }
 
+// Panics if either dependency is out of listener slots in a fixed-capacity build
 pub fn derived2<
     Computation: Clone + PartialEq + 'static,
     Compute: Fn((D0, D1)) -> Computation + 'static,
@@ -109,27 +138,45 @@ pub fn derived2<
     deps: (&OD0, &OD1),
     compute: Compute,
 ) -> DerivedSignal<Computation, (D0, D1), Compute> {
+    try_derived2(deps, compute).expect("no room left for another listener")
+}
+
+// Subscribes to neither dependency if it can't subscribe to both
+pub fn try_derived2<
+    Computation: Clone + PartialEq + 'static,
+    Compute: Fn((D0, D1)) -> Computation + 'static,
+    D0: Clone + 'static,
+    D1: Clone + 'static,
+    OD0: Observable<D0>,
+    OD1: Observable<D1>,
+>(
+    deps: (&OD0, &OD1),
+    compute: Compute,
+) -> Result<DerivedSignal<Computation, (D0, D1), Compute>, CapacityError> {
     let ds = DerivedSignal {
         data: Rc::new(RefCell::new(DerivedSignalData {
             cache: None,
             deps: (deps.0.peek(), deps.1.peek()),
             compute,
-            listeners: Vec::with_capacity(4),
+            listeners: ArbitraryIdStore::new(),
         })),
     };
     let ds_clone = ds.data.clone();
-    deps.0.subscribe(move |new| {
+    let first = deps.0.try_subscribe(move |new| {
         let mut borrowed = ds_clone.borrow_mut();
         borrowed.deps.0 = new;
         borrowed.maybe_recompute();
-    });
+    })?;
     let ds_clone = ds.data.clone();
-    deps.1.subscribe(move |new| {
+    let second = deps.1.try_subscribe(move |new| {
         let mut borrowed = ds_clone.borrow_mut();
         borrowed.deps.1 = new;
         borrowed.maybe_recompute();
     });
-    ds
+    if second.is_err() {
+        deps.0.unsubscribe(first);
+    }
+    second.map(|_| ds)
 }
 
 struct DerivedSignalData<
@@ -140,7 +187,7 @@ struct DerivedSignalData<
     cache: Option<Derivation>,
     deps: Deps,
     compute: F,
-    listeners: Vec<Option<Rc<RefCell<dyn FnMut(Derivation)>>>>,
+    listeners: Listeners<Derivation>,
 }
 
 #[derive(Clone)]
@@ -164,24 +211,15 @@ impl<T: Clone + PartialEq, Deps: Clone, F: Clone + Fn(Deps) -> T> Observable<T>
         data.cache.clone().unwrap()
     }
 
-    fn subscribe<OnChange: FnMut(T) + 'static>(&self, on_change: OnChange) -> usize {
-        let mut data = self.data.borrow_mut();
-        let chosen_index = data
-            .listeners
-            .iter()
-            .enumerate()
-            .find(|(_, v)| v.is_none())
-            .map(|i| i.0);
-        if let Some(i) = chosen_index {
-            data.listeners[i] = Some(Rc::new(RefCell::new(on_change)));
-        } else {
-            data.listeners.push(Some(Rc::new(RefCell::new(on_change))));
-        }
-        data.listeners.len() - 1
+    fn try_subscribe<OnChange: FnMut(T) + 'static>(
+        &self,
+        on_change: OnChange,
+    ) -> Result<SubscriptionId, CapacityError> {
+        add_listener(&mut self.data.borrow_mut().listeners, on_change)
     }
 
-    fn unsubscribe(&self, _id: usize) {
-        //todo!()
+    fn unsubscribe(&self, id: SubscriptionId) {
+        self.data.borrow_mut().listeners.delete(id.0);
     }
 }
 
@@ -190,7 +228,7 @@ impl<Derivation: Clone + PartialEq, Deps: Clone, F: Fn(Deps) -> Derivation>
 {
     pub fn maybe_recompute(&mut self) {
         // TODO: do we need to check deps?
-        if self.listeners.is_empty() {
+        if self.listeners.values().next().is_none() {
             self.cache = None;
             return;
         }
@@ -201,10 +239,8 @@ impl<Derivation: Clone + PartialEq, Deps: Clone, F: Fn(Deps) -> Derivation>
             return;
         }
         let new = self.cache.clone().unwrap();
-        for i in 0..self.listeners.len() {
-            if let Some(listener) = &self.listeners[i] {
-                (listener.borrow_mut())(new.clone());
-            }
+        for listener in self.listeners.values() {
+            (listener.borrow_mut())(new.clone());
         }
     }
 }
@@ -219,9 +255,13 @@ impl<T: Clone> Observable<T> for Constant<T> {
         self.value.clone()
     }
 
-    fn subscribe<F: FnMut(T) + 'static>(&self, _: F) -> usize {
-        0
+    fn try_subscribe<F: FnMut(T) + 'static>(&self, _: F) -> Result<SubscriptionId, CapacityError> {
+        // Never changes, so nothing is stored - the id is out of range for every other observable
+        Ok(SubscriptionId(SlotKey {
+            index: usize::MAX,
+            generation: 0,
+        }))
     }
 
-    fn unsubscribe(&self, _: usize) {}
+    fn unsubscribe(&self, _: SubscriptionId) {}
 }
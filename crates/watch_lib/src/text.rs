@@ -0,0 +1,263 @@
+use crate::{Align, Font};
+use core::cmp::max;
+use core::ops::Range;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Wrap {
+    // Everything up to a newline stays on one line
+    #[default]
+    None,
+    // Lines break between words, or mid-word for words too long to fit on a line of their own
+    Word,
+}
+
+pub const MAX_TEXT_SCALE: u8 = 6;
+
+// How many screen pixels each font pixel covers, across and down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scale {
+    pub x: u8,
+    pub y: u8,
+}
+
+impl Scale {
+    pub fn uniform(factor: u8) -> Scale {
+        Scale {
+            x: factor,
+            y: factor,
+        }
+    }
+    // Factors outside 1..=MAX_TEXT_SCALE draw at the nearest one that's in range
+    pub(crate) fn factors(self) -> (i16, i16) {
+        let clamp = |factor: u8| factor.clamp(1, MAX_TEXT_SCALE) as i16;
+        (clamp(self.x), clamp(self.y))
+    }
+}
+
+impl Default for Scale {
+    fn default() -> Scale {
+        Scale::uniform(1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextStyle {
+    pub wrap: Wrap,
+    // Starts a new line at each '\n' - otherwise they show as spaces
+    pub newlines: bool,
+    // Where each line sits across the element
+    pub horizontal: Align,
+    // Where the block of lines sits down the element
+    pub vertical: Align,
+    // Ends text that doesn't fit with "..." rather than cutting it off
+    pub ellipsis: bool,
+    pub scale: Scale,
+}
+
+pub(crate) const ELLIPSIS: &str = "...";
+
+// The chunk'th 8 pixels of a glyph row once each of its pixels is repeated scale times, MSB first
+pub(crate) fn stretched_byte(row: u32, scale: i16, chunk: i16) -> u8 {
+    if scale == 1 {
+        return ((row << (chunk * 8)) >> 24) as u8;
+    }
+    let mut byte = 0;
+    for bit in 0..8 {
+        let column = (chunk * 8 + bit) / scale;
+        if column < 32 && row & (0x8000_0000 >> column) != 0 {
+            byte |= 0x80 >> bit;
+        }
+    }
+    byte
+}
+
+// ORs 8 pixels starting at x into a packed row, dropping any that fall outside it
+pub(crate) fn or_byte(row: &mut [u8], x: i16, bits: u8) {
+    if bits == 0 {
+        return;
+    }
+    let placed = ((bits as u16) << 8 >> x.rem_euclid(8)).to_be_bytes();
+    let first = x.div_euclid(8);
+    for (i, byte) in placed.into_iter().enumerate() {
+        let index = first + i as i16;
+        if index >= 0 && (index as usize) < row.len() {
+            row[index as usize] |= byte;
+        }
+    }
+}
+
+// Newlines that don't start a line are drawn as spaces
+pub(crate) fn shown_as(c: char) -> char {
+    if c == '\n' { ' ' } else { c }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextLine {
+    // Byte range of the line within the text, without the spaces it was wrapped at
+    pub range: Range<usize>,
+    // In pixels, including the ellipsis
+    pub width: i16,
+    // Whether the line is cut short and ends in an ellipsis
+    pub ellipsis: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextSize {
+    pub width: i16,
+    // Always room for at least one line, even when there's no text
+    pub height: i16,
+    pub line_count: usize,
+}
+
+// Distance between the tops of lines, once scaled
+pub(crate) fn line_height(font: &dyn Font, scale: Scale) -> i16 {
+    let (_, scale_y) = scale.factors();
+    max(1, font.height() as i16) * scale_y
+}
+
+// How far along each character moves the next, once scaled
+pub(crate) fn advance(font: &dyn Font, scale: Scale) -> impl Fn(char) -> i16 + '_ {
+    let (scale_x, _) = scale.factors();
+    move |c| font.advance(shown_as(c)) as i16 * scale_x
+}
+
+// The lines text is drawn as, wrapping or ending in an ellipsis at max_width if there is one
+pub fn text_lines<'a>(
+    font: &'a dyn Font,
+    text: &'a str,
+    style: TextStyle,
+    max_width: Option<u8>,
+) -> impl Iterator<Item = TextLine> + 'a {
+    let max_width = max_width.map_or(i16::MAX, i16::from);
+    Lines::new(
+        text,
+        style,
+        max_width,
+        usize::MAX,
+        advance(font, style.scale),
+    )
+}
+
+pub fn measure_text(
+    font: &dyn Font,
+    text: &str,
+    style: TextStyle,
+    max_width: Option<u8>,
+) -> TextSize {
+    let mut size = TextSize::default();
+    for line in text_lines(font, text, style, max_width) {
+        size.width = max(size.width, line.width);
+        size.line_count += 1;
+    }
+    size.height = (max(1, size.line_count) as i16).saturating_mul(line_height(font, style.scale));
+    size
+}
+
+// Splits text into the lines it's drawn as, given each character's advance in pixels
+pub(crate) struct Lines<'a, A: Fn(char) -> i16> {
+    text: &'a str,
+    style: TextStyle,
+    max_width: i16,
+    max_lines: usize,
+    advance: A,
+    position: usize,
+    line_count: usize,
+}
+
+impl<'a, A: Fn(char) -> i16> Lines<'a, A> {
+    pub(crate) fn new(
+        text: &'a str,
+        style: TextStyle,
+        max_width: i16,
+        max_lines: usize,
+        advance: A,
+    ) -> Self {
+        Lines {
+            text,
+            style,
+            max_width,
+            max_lines,
+            advance,
+            position: 0,
+            line_count: 0,
+        }
+    }
+    fn width(&self, text: &str) -> i16 {
+        text.chars()
+            .map(|c| (self.advance)(c))
+            .fold(0, i16::saturating_add)
+    }
+}
+
+impl<A: Fn(char) -> i16> Iterator for Lines<'_, A> {
+    type Item = TextLine;
+
+    fn next(&mut self) -> Option<TextLine> {
+        if self.position >= self.text.len() || self.line_count >= self.max_lines {
+            return None;
+        }
+        let start = self.position;
+        let mut end = self.text.len();
+        let mut next_start = self.text.len();
+        let mut soft_break = false;
+        let mut width: i16 = 0;
+        // End of the last word on the line, and where the word after it starts
+        let mut last_space: Option<(usize, usize)> = None;
+        for (i, c) in self.text[start..].char_indices() {
+            let i = start + i;
+            if c == '\n' && self.style.newlines {
+                (end, next_start) = (i, i + 1);
+                break;
+            }
+            let advance = (self.advance)(c);
+            if self.style.wrap == Wrap::Word
+                && i > start
+                && width.saturating_add(advance) > self.max_width
+            {
+                soft_break = true;
+                (end, next_start) = if c == ' ' || c == '\n' {
+                    (i, i)
+                } else {
+                    last_space.unwrap_or((i, i))
+                };
+                break;
+            }
+            if c == ' ' || c == '\n' {
+                last_space = Some((i, i + 1));
+            }
+            width = width.saturating_add(advance);
+        }
+        if soft_break {
+            // Spaces at a wrap belong to neither line
+            end = start + self.text[start..end].trim_end_matches([' ', '\n']).len();
+            next_start += self.text[next_start..].len()
+                - self.text[next_start..]
+                    .trim_start_matches([' ', '\n'])
+                    .len();
+        }
+        self.position = next_start;
+        self.line_count += 1;
+
+        let mut line = TextLine {
+            range: start..end,
+            width: self.width(&self.text[start..end]),
+            ellipsis: false,
+        };
+        let cut_short = self.line_count == self.max_lines && next_start < self.text.len();
+        if self.style.ellipsis && (cut_short || line.width > self.max_width) {
+            let ellipsis_width = self.width(ELLIPSIS);
+            let mut kept = &self.text[start..end];
+            while !kept.is_empty()
+                && self.width(kept).saturating_add(ellipsis_width) > self.max_width
+            {
+                let last = kept.chars().next_back().unwrap();
+                kept = &kept[..kept.len() - last.len_utf8()];
+            }
+            let kept = kept.trim_end_matches(' ');
+            line.range = start..start + kept.len();
+            line.width = self.width(kept).saturating_add(ellipsis_width);
+            line.ellipsis = true;
+        }
+        Some(line)
+    }
+}
@@ -0,0 +1,148 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::{pixel, rect, text};
+use watch_lib::bdf::{BdfError, BdfFont};
+use watch_lib::{
+    BitmapFont, BoundingRect, ElementId, Font, Font8x8, Layout, RectUIElement, Signal, TRANSPARENT,
+    TextStyle, TextUIElement, UIContext, Wrap,
+};
+
+const TINY: &str = include_str!("fonts/tiny.bdf");
+
+fn tiny_font() -> &'static BitmapFont<'static> {
+    let bdf: &'static BdfFont = Box::leak(Box::new(BdfFont::parse(TINY).unwrap()));
+    Box::leak(Box::new(bdf.font()))
+}
+
+fn new_context() -> UIContext {
+    let mut ctx = UIContext::new(tiny_font());
+    ctx.handle_draw_requests();
+    ctx
+}
+
+#[test]
+fn reads_font_metrics() {
+    let font = BdfFont::parse(TINY).unwrap();
+    assert_eq!(
+        font.name,
+        "-test-tiny-medium-r-normal--7-70-75-75-p-40-iso10646-1"
+    );
+    assert_eq!((font.height, font.baseline, font.default_char), (7, 5, '?'));
+    // Sorted, without the glyph that has no encoding
+    let chars: Vec<char> = font.glyphs.iter().map(|glyph| glyph.c).collect();
+    assert_eq!(chars, [' ', '?', 'T', 'g', 'i', 'm']);
+}
+
+#[test]
+fn places_glyphs_on_the_baseline() {
+    let font = BdfFont::parse(TINY).unwrap();
+    let font = font.font();
+    assert_eq!(font.advance('i'), 2);
+    assert_eq!(font.advance('m'), 6);
+    // The x offset moves the bitmap right
+    assert_eq!(font.glyph_width('T'), 4);
+    assert_eq!(font.glyph_row('T', 0), 0x70 << 24);
+    // Short glyphs sit on the baseline, and descenders hang below it
+    assert_eq!(font.glyph_row('m', 1), 0);
+    assert_eq!(font.glyph_row('m', 2), 0xD0 << 24);
+    assert_eq!(font.glyph_row('m', 4), 0xA8 << 24);
+    assert_eq!(font.glyph_row('g', 3), 0xE0 << 24);
+    assert_eq!(font.glyph_row('g', 6), 0x20 << 24);
+    // Missing characters are drawn as the default
+    assert_eq!(font.advance('x'), 4);
+    assert_eq!(font.glyph_row('x', 0), 0xE0 << 24);
+}
+
+#[test]
+fn only_stores_rows_with_pixels() {
+    let font = BdfFont::parse(TINY).unwrap();
+    let m = font.glyphs.iter().find(|glyph| glyph.c == 'm').unwrap();
+    assert_eq!((m.top, m.rows), (2, 3));
+    let space = font.glyphs.iter().find(|glyph| glyph.c == ' ').unwrap();
+    assert_eq!((space.advance, space.rows), (3, 0));
+    // 5 + 5 + 4 + 5 + 3 rows, each a byte
+    assert_eq!(font.bitmap.len(), 22);
+}
+
+#[test]
+fn writes_a_static_font() {
+    let source = BdfFont::parse(TINY).unwrap().to_rust("TINY");
+    assert!(
+        source
+            .contains("pub static TINY: watch_lib::BitmapFont<'static> = watch_lib::BitmapFont {")
+    );
+    assert!(source.contains("default_char: '?',"));
+    assert!(source.contains(
+        "watch_lib::BitmapGlyph { c: 'm', advance: 6, width: 5, top: 2, rows: 3, offset: 19 },"
+    ));
+    assert!(source.contains("0xd0, 0xa8, 0xa8,"));
+}
+
+#[test]
+fn reports_the_line_of_bad_input() {
+    let bad_hex = TINY.replacen("A0\n", "Z0\n", 1);
+    assert_eq!(
+        BdfFont::parse(&bad_hex).unwrap_err(),
+        BdfError {
+            line: 49,
+            message: "bitmap rows should be hex"
+        }
+    );
+
+    let missing_row = TINY.replacen("D0\n", "", 1);
+    assert_eq!(
+        BdfFont::parse(&missing_row).unwrap_err().message,
+        "bitmap doesn't match the BBX height"
+    );
+
+    let unfinished = TINY.split("STARTCHAR m").next().unwrap().to_string() + "STARTCHAR m\n";
+    assert_eq!(
+        BdfFont::parse(&unfinished).unwrap_err().message,
+        "missing ENDCHAR"
+    );
+}
+
+#[test]
+fn renders_with_proportional_advances() {
+    let mut ctx = new_context();
+    text(&mut ctx, "mi", rect(0, 0, 20, 7), TextStyle::default());
+    ctx.handle_draw_requests();
+
+    let row: Vec<bool> = (0..8).map(|x| pixel(&ctx, x, 2)).collect();
+    assert_eq!(row, [true, true, false, true, false, false, true, false]);
+    assert!(pixel(&ctx, 6, 0));
+    assert!(!pixel(&ctx, 6, 1));
+}
+
+#[test]
+fn wraps_and_sizes_with_proportional_advances() {
+    let mut ctx = new_context();
+    let wrapped = TextStyle {
+        wrap: Wrap::Word,
+        ..TextStyle::default()
+    };
+    // "i m" is 11 pixels and "m i" too, so each word needs a line of its own
+    text(&mut ctx, "i m i", rect(0, 0, 8, 21), wrapped);
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 0, 0));
+    assert!(pixel(&ctx, 1, 9));
+    assert!(pixel(&ctx, 0, 14));
+    assert!(!pixel(&ctx, 3, 0));
+
+    let column = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(rect(50, 50, 100, 100), TRANSPARENT),
+    );
+    ctx.set_layout(column, Some(Layout::column()));
+    let signal = Signal::new("mim".to_string());
+    let sized = ctx.mount(column, TextUIElement::new(&signal, BoundingRect::default()));
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(sized), Some(rect(0, 0, 14, 7)));
+
+    // Other elements can still use a monospace font
+    ctx.set_font(sized, &Font8x8);
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(sized), Some(rect(0, 0, 24, 8)));
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{ElementId, RectUIElement, Signal, TextUIElement};
+
+#[test]
+fn clipping_containers_cut_off_their_subtree() {
+    let mut ctx = new_context();
+    let card = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(10, 10, 20, 20), 0));
+    let row = ctx.mount(card, RectUIElement::new(rect(0, 0, 40, 10), 0));
+    // Hangs off a child that doesn't clip, but still can't escape the card
+    ctx.mount(row, RectUIElement::new(rect(15, 0, 20, 5), 1));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 40, 10));
+
+    assert!(ctx.set_clips_children(card, true));
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert!(pixel(&ctx, 29, 10));
+    assert!(!pixel(&ctx, 30, 10));
+    assert!(!pixel(&ctx, 40, 10));
+
+    assert!(ctx.set_clips_children(card, false));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 40, 10));
+}
+
+#[test]
+fn partial_redraws_stay_inside_the_clip() {
+    let mut ctx = new_context();
+    let text = Signal::new("scrolling".to_string());
+    let view = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(16, 16, 32, 8), 0));
+    ctx.set_clips_children(view, true);
+    ctx.mount(view, TextUIElement::new(&text, rect(-8, 0, 72, 8)));
+    // Neighbours either side of the view
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 16, 16, 8), 1));
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(48, 16, 16, 8), 1));
+    ctx.handle_draw_requests();
+
+    text.set("scrolled!".to_string());
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    let view_rect = rect(16, 16, 32, 8);
+    for region in report.regions {
+        assert_eq!(region.intersection(&view_rect), Some(*region));
+    }
+    for y in 16..24 {
+        for x in (0..16).chain(48..64) {
+            assert!(pixel(&ctx, x, y));
+        }
+    }
+}
+
+#[test]
+fn children_clipped_out_entirely_are_not_painted() {
+    let mut ctx = new_context();
+    let card = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 10, 10), 0));
+    ctx.set_clips_children(card, true);
+    ctx.mount(card, RectUIElement::new(rect(20, 20, 10, 10), 1));
+    let report = ctx.handle_draw_requests();
+    assert!(
+        report
+            .regions
+            .iter()
+            .all(|region| region.x < 10 && region.y < 10)
+    );
+    assert!(!pixel(&ctx, 25, 25));
+}
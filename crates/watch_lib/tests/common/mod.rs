@@ -0,0 +1,35 @@
+// Helpers shared by the integration tests. Each test binary only uses some of them.
+#![allow(dead_code)]
+
+use watch_lib::{
+    BoundingRect, ElementId, Font8x8, ROW_BYTES, Signal, TextStyle, TextUIElement, UIContext,
+};
+
+// A context in the default font that's already done its first full redraw
+pub fn new_context() -> UIContext {
+    let mut ctx = UIContext::new(&Font8x8);
+    ctx.handle_draw_requests();
+    ctx
+}
+
+pub fn rect(x: i16, y: i16, width: u8, height: u8) -> BoundingRect {
+    BoundingRect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+pub fn pixel(ctx: &UIContext, x: usize, y: usize) -> bool {
+    ctx.get_screen_buffer()[y * ROW_BYTES + x / 8] & (0x80 >> (x % 8)) != 0
+}
+
+// Mounts a text element under the root showing a fixed value
+pub fn text(ctx: &mut UIContext, value: &str, area: BoundingRect, style: TextStyle) -> ElementId {
+    let signal = Signal::new(value.to_string());
+    ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::with_style(&signal, area, style),
+    )
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{
+    BoundingRect, CapacityError, CompositeMode, ElementId, RectUIElement, Signal, TRANSPARENT,
+    TextUIElement, UIContext, UIElement,
+};
+
+// Vertical stripes, with every other stripe left out when holey
+struct Stripes {
+    rect: BoundingRect,
+    holey: bool,
+    next_element_id: Option<ElementId>,
+}
+
+impl Stripes {
+    fn new(rect: BoundingRect, holey: bool) -> Stripes {
+        Stripes {
+            rect,
+            holey,
+            next_element_id: None,
+        }
+    }
+}
+
+impl UIElement for Stripes {
+    fn mount_to_context(&self, _ctx: &UIContext, _id: ElementId) -> Result<(), CapacityError> {
+        Ok(())
+    }
+    fn get_pixel(&self, _ctx: &UIContext, x: u8, _y: u8) -> u8 {
+        match x % 2 {
+            0 => 1,
+            _ if self.holey => TRANSPARENT,
+            _ => 0,
+        }
+    }
+    fn get_bounding_rect(&self) -> BoundingRect {
+        self.rect
+    }
+    fn get_first_child_id(&self) -> Option<ElementId> {
+        None
+    }
+    fn get_next_element_id(&self) -> Option<ElementId> {
+        self.next_element_id
+    }
+    fn set_next_element_id(&mut self, id: Option<ElementId>) {
+        self.next_element_id = id
+    }
+    fn insert_child_at_end(&mut self, _ctx: &mut UIContext, _id: ElementId) {
+        panic!("Stripes does not support children");
+    }
+}
+
+#[test]
+fn the_root_is_invisible() {
+    let mut ctx = new_context();
+    assert!(ctx.get_screen_buffer().iter().all(|&byte| byte == 0));
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 10, 10), 1));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 5, 5));
+}
+
+#[test]
+fn transparent_pixels_show_what_is_underneath() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 20, 4), 1));
+    ctx.mount(ElementId::ROOT, Stripes::new(rect(0, 0, 20, 4), true));
+    let cutout = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(10, 0, 10, 4), 0));
+    ctx.set_composite_mode(cutout, CompositeMode::Mask);
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 0, 0));
+    assert!(pixel(&ctx, 1, 0));
+    assert!(!pixel(&ctx, 10, 0));
+    assert!(!pixel(&ctx, 11, 0));
+
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 20, 4), 1));
+    ctx.mount(ElementId::ROOT, Stripes::new(rect(0, 0, 20, 4), false));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 0, 0));
+    assert!(!pixel(&ctx, 1, 0));
+}
+
+#[test]
+fn or_mode_lays_text_over_a_pattern() {
+    let mut ctx = new_context();
+    let text = Signal::new("Hi".to_string());
+    ctx.mount(ElementId::ROOT, Stripes::new(rect(0, 0, 16, 8), false));
+    let label = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&text, rect(0, 0, 16, 8)),
+    );
+    ctx.set_composite_mode(label, CompositeMode::Or);
+    ctx.handle_draw_requests();
+    let layered = ctx.get_screen_buffer().to_vec();
+
+    let mut stripes_only = new_context();
+    stripes_only.mount(ElementId::ROOT, Stripes::new(rect(0, 0, 16, 8), false));
+    stripes_only.handle_draw_requests();
+    let mut text_only = new_context();
+    text_only.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&text, rect(0, 0, 16, 8)),
+    );
+    text_only.handle_draw_requests();
+    for (i, byte) in layered.iter().enumerate() {
+        assert_eq!(
+            *byte,
+            stripes_only.get_screen_buffer()[i] | text_only.get_screen_buffer()[i]
+        );
+    }
+}
+
+#[test]
+fn xor_highlights_survive_partial_redraws_underneath() {
+    let mut ctx = new_context();
+    let text = Signal::new("Menu".to_string());
+    ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&text, rect(4, 4, 32, 8)),
+    );
+    let highlight = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 2, 40, 12), 1));
+    ctx.set_composite_mode(highlight, CompositeMode::Xor);
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 0, 2));
+    assert!(pixel(&ctx, 3, 4));
+
+    text.set("Back".to_string());
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+
+    let mut plain = new_context();
+    plain.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&text, rect(4, 4, 32, 8)),
+    );
+    plain.handle_draw_requests();
+    for y in 0..20 {
+        for x in 0..48 {
+            let highlighted = (2..14).contains(&y) && x < 40;
+            assert_eq!(pixel(&ctx, x, y), pixel(&plain, x, y) != highlighted);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use watch_lib::{
+    BoundingRect, DisplaySink, ElementId, Font8x8, RecordedUpdate, RecordingSink, RectUIElement,
+    Signal, TextUIElement, UIContext,
+};
+
+fn new_context() -> UIContext {
+    UIContext::new(&Font8x8)
+}
+
+#[test]
+fn partial_updates_keep_the_display_in_sync() {
+    let mut ctx = new_context();
+    let mut sink = RecordingSink::new();
+    let text = Signal::new("12:00".to_string());
+    let card = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(
+            BoundingRect {
+                x: 13,
+                y: 17,
+                width: 60,
+                height: 20,
+            },
+            1,
+        ),
+    );
+    ctx.mount(
+        card,
+        TextUIElement::new(
+            &text,
+            BoundingRect {
+                x: 3,
+                y: 3,
+                width: 48,
+                height: 8,
+            },
+        ),
+    );
+    sink.present(&ctx.handle_draw_requests()).unwrap();
+    text.set("12:01".to_string());
+    sink.present(&ctx.handle_draw_requests()).unwrap();
+
+    assert_eq!(sink.frame(), ctx.get_screen_buffer());
+    assert_eq!(
+        sink.updates().last(),
+        Some(&RecordedUpdate::Partial(vec![BoundingRect {
+            x: 16,
+            y: 20,
+            width: 48,
+            height: 8,
+        }]))
+    );
+}
+
+#[test]
+fn nothing_is_sent_when_nothing_changed() {
+    let mut ctx = new_context();
+    let mut sink = RecordingSink::new();
+    sink.present(&ctx.handle_draw_requests()).unwrap();
+    assert!(sink.updates().is_empty());
+}
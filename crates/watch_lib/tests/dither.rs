@@ -0,0 +1,92 @@
+use watch_lib::{Bitmap, Dither, dither};
+
+const METHODS: [Dither; 4] = [
+    Dither::Threshold,
+    Dither::FloydSteinberg,
+    Dither::Atkinson,
+    Dither::Bayer,
+];
+
+fn dithered(gray: &[u8], width: u8, height: u8, method: Dither) -> Vec<u8> {
+    let mut out = vec![0xAA; width.div_ceil(8) as usize * height as usize];
+    dither(gray, width, height, method, &mut out);
+    out
+}
+
+// The share of pixels set when a flat gray is dithered
+fn coverage(level: u8, method: Dither) -> f32 {
+    let out = dithered(&[level; 64 * 64], 64, 64, method);
+    out.iter().map(|byte| byte.count_ones()).sum::<u32>() as f32 / (64.0 * 64.0)
+}
+
+#[test]
+fn black_and_white_stay_solid() {
+    for method in METHODS {
+        assert_eq!(coverage(0, method), 1.0, "{method:?}");
+        assert_eq!(coverage(255, method), 0.0, "{method:?}");
+    }
+}
+
+#[test]
+fn thresholding_splits_at_half() {
+    assert_eq!(
+        dithered(&[127, 128, 0, 255], 4, 1, Dither::Threshold),
+        [0xA0]
+    );
+}
+
+#[test]
+fn packs_rows_like_bitmaps() {
+    // 10 wide, so each row takes 2 bytes and the padding bits are left clear
+    let gray: Vec<u8> = (0..20).map(|i| if i % 10 == 9 { 0 } else { 255 }).collect();
+    let out = dithered(&gray, 10, 2, Dither::Threshold);
+    assert_eq!(out, [0x00, 0x40, 0x00, 0x40]);
+    let bitmap = Bitmap {
+        width: 10,
+        height: 2,
+        data: &out,
+        mask: None,
+    };
+    assert_eq!(bitmap.get_pixel(9, 1), 1);
+}
+
+#[test]
+fn bayer_turns_half_gray_into_a_checkerboard() {
+    let out = dithered(&[128; 16 * 4], 16, 4, Dither::Bayer);
+    assert_eq!(out, [0x55, 0x55, 0xAA, 0xAA, 0x55, 0x55, 0xAA, 0xAA]);
+    // Each pixel is dithered the same whatever is around it
+    let mut gray = [128; 16 * 4];
+    gray[..16].fill(0);
+    assert_eq!(dithered(&gray, 16, 4, Dither::Bayer)[2..], out[2..]);
+}
+
+#[test]
+fn error_diffusion_keeps_the_brightness() {
+    for level in [32, 96, 160, 224] {
+        let dark = 1.0 - level as f32 / 255.0;
+        for method in [Dither::FloydSteinberg, Dither::Bayer] {
+            let coverage = coverage(level, method);
+            assert!(
+                (coverage - dark).abs() < 0.02,
+                "{method:?} {level}: {coverage}"
+            );
+        }
+        assert!(matches!(coverage(level, Dither::Threshold), 0.0 | 1.0));
+    }
+    // Atkinson drops a quarter of the error, so it's only close in the middle tones
+    let coverage = coverage(128, Dither::Atkinson);
+    assert!((coverage - 0.5).abs() < 0.05, "{coverage}");
+}
+
+#[test]
+fn atkinson_clips_near_black_and_white() {
+    assert_eq!(coverage(12, Dither::Atkinson), 1.0);
+    assert_eq!(coverage(243, Dither::Atkinson), 0.0);
+    assert!(coverage(12, Dither::FloydSteinberg) < 1.0);
+}
+
+#[test]
+#[should_panic(expected = "not enough room to dither into")]
+fn checks_the_output_fits() {
+    dither(&[0; 9 * 2], 9, 2, Dither::Threshold, &mut [0; 3]);
+}
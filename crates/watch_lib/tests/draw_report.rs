@@ -0,0 +1,108 @@
+mod common;
+
+use common::{new_context, rect};
+use watch_lib::{BufferWindow, ElementId, ROW_BYTES, RectUIElement};
+
+fn ranges(window: &BufferWindow) -> Vec<std::ops::Range<usize>> {
+    window.row_byte_ranges().collect()
+}
+
+#[test]
+fn windows_round_out_to_whole_bytes() {
+    let window = BufferWindow::covering(&rect(3, 10, 2, 4));
+    assert_eq!(
+        (window.rows.clone(), window.byte_columns.clone()),
+        (10..14, 0..1)
+    );
+    assert_eq!(window.byte_range(), 10 * ROW_BYTES..13 * ROW_BYTES + 1);
+    assert_eq!(ranges(&window), [250..251, 275..276, 300..301, 325..326]);
+
+    // Starts and ends mid-byte, straddling two byte boundaries
+    let window = BufferWindow::covering(&rect(13, 0, 10, 2));
+    assert_eq!(window.byte_columns, 1..3);
+    assert_eq!(window.byte_range(), 1..ROW_BYTES + 3);
+    assert_eq!(ranges(&window), [1..3, 26..28]);
+}
+
+#[test]
+fn windows_are_clipped_to_the_screen() {
+    let window = BufferWindow::covering(&rect(195, 198, 10, 5));
+    assert_eq!(
+        (window.rows.clone(), window.byte_columns.clone()),
+        (198..200, 24..25)
+    );
+    assert_eq!(window.byte_range(), 198 * ROW_BYTES + 24..200 * ROW_BYTES);
+    assert_eq!(ranges(&window), [4974..4975, 4999..5000]);
+
+    let window = BufferWindow::covering(&rect(-6, -2, 10, 4));
+    assert_eq!(
+        (window.rows.clone(), window.byte_columns.clone()),
+        (0..2, 0..1)
+    );
+
+    let window = BufferWindow::covering(&rect(200, 10, 8, 8));
+    assert_eq!(window.byte_range(), 0..0);
+    assert!(ranges(&window).is_empty());
+}
+
+#[test]
+fn nothing_changed_means_no_windows() {
+    let mut ctx = new_context();
+    let report = ctx.handle_draw_requests();
+    assert!(report.is_empty());
+    assert!(!report.full_refresh);
+    assert_eq!(report.windows().count(), 0);
+}
+
+#[test]
+fn lots_of_changes_refresh_everything() {
+    let mut ctx = new_context();
+    for i in 0..20 {
+        ctx.mount(ElementId::ROOT, RectUIElement::new(rect(i * 9, 0, 2, 2), 1));
+    }
+    let report = ctx.handle_draw_requests();
+    assert!(report.full_refresh);
+    assert_eq!(report.regions, [rect(0, 0, 200, 200)]);
+    assert_eq!(report.aligned_regions, [rect(0, 0, 200, 200)]);
+}
+
+#[test]
+fn windows_cover_every_changed_byte() {
+    let mut ctx = new_context();
+    let dirty = [
+        rect(13, 20, 10, 2),
+        rect(190, 100, 10, 3),
+        rect(61, 150, 1, 1),
+    ];
+    for area in dirty {
+        ctx.mount(ElementId::ROOT, RectUIElement::new(area, 1));
+    }
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    let mut covered = vec![false; report.screen_buffer.len()];
+    for window in report.windows() {
+        assert!(window.byte_range().end <= report.screen_buffer.len());
+        for range in window.row_byte_ranges() {
+            assert!(
+                window.byte_range().start <= range.start && range.end <= window.byte_range().end
+            );
+            covered[range].fill(true);
+        }
+    }
+    for area in dirty {
+        let window = BufferWindow::covering(&area);
+        for range in window.row_byte_ranges() {
+            assert!(
+                covered[range.clone()].iter().all(|&c| c),
+                "{area:?} at {range:?}"
+            );
+            assert!(report.screen_buffer[range].iter().all(|&byte| byte != 0));
+        }
+    }
+    for (i, &byte) in report.screen_buffer.iter().enumerate() {
+        assert!(
+            byte == 0 || covered[i],
+            "byte {i} changed outside the windows"
+        );
+    }
+}
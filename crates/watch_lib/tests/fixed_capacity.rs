@@ -0,0 +1,98 @@
+#![cfg(feature = "fixed-capacity")]
+
+use watch_lib::{
+    BoundingRect, CapacityError, ElementId, Font8x8, MAX_ELEMENTS, MAX_LISTENERS, Observable,
+    RectUIElement, Signal, TextUIElement, UIContext, try_derived, try_derived2,
+};
+
+fn small_rect(i: usize) -> BoundingRect {
+    BoundingRect {
+        x: (i % 20) as i16 * 10,
+        y: (i / 20) as i16 * 10,
+        width: 4,
+        height: 4,
+    }
+}
+
+#[test]
+fn mounting_past_capacity_is_an_error() {
+    let mut ctx = UIContext::new(&Font8x8);
+    // The root takes up the first slot
+    let mut parent = ElementId::ROOT;
+    for i in 1..MAX_ELEMENTS {
+        parent = ctx
+            .try_mount(parent, RectUIElement::new(small_rect(i), 1))
+            .unwrap();
+    }
+    assert_eq!(
+        ctx.try_mount(parent, RectUIElement::new(small_rect(0), 1)),
+        Err(CapacityError)
+    );
+    // Running out of slots doesn't stop the existing tree from drawing
+    assert!(ctx.handle_draw_requests().full_refresh);
+}
+
+#[test]
+fn subscribing_past_capacity_is_an_error() {
+    let signal = Signal::new(0);
+    for _ in 0..MAX_LISTENERS {
+        signal.try_subscribe(|_| {}).unwrap();
+    }
+    assert_eq!(signal.try_subscribe(|_| {}), Err(CapacityError));
+}
+
+#[test]
+fn mounting_text_on_a_full_signal_is_an_error() {
+    let mut ctx = UIContext::new(&Font8x8);
+    let text = Signal::new(String::from("12:00"));
+    for i in 0..MAX_LISTENERS {
+        ctx.try_mount(ElementId::ROOT, TextUIElement::new(&text, small_rect(i)))
+            .unwrap();
+    }
+    let label = TextUIElement::new(&text, small_rect(MAX_LISTENERS));
+    assert_eq!(ctx.try_mount(ElementId::ROOT, label), Err(CapacityError));
+
+    // The failed element gave its slot back, so there's still room for the rest of the tree
+    let mut parent = ElementId::ROOT;
+    for i in 1 + MAX_LISTENERS..MAX_ELEMENTS {
+        parent = ctx
+            .try_mount(parent, RectUIElement::new(small_rect(i), 1))
+            .unwrap();
+    }
+    ctx.handle_draw_requests();
+}
+
+#[test]
+fn binding_visibility_to_a_full_signal_is_an_error() {
+    let mut ctx = UIContext::new(&Font8x8);
+    let icon = ctx.mount(ElementId::ROOT, RectUIElement::new(small_rect(0), 1));
+    let shown = Signal::new(true);
+    assert_eq!(ctx.try_bind_visibility(icon, &shown), Ok(true));
+    let hidden = Signal::new(false);
+    for _ in 0..MAX_LISTENERS {
+        hidden.try_subscribe(|_| {}).unwrap();
+    }
+    assert_eq!(ctx.try_bind_visibility(icon, &hidden), Err(CapacityError));
+
+    // Still following the first binding
+    assert!(ctx.is_visible(icon));
+    shown.set(false);
+    ctx.handle_draw_requests();
+    assert!(!ctx.is_visible(icon));
+}
+
+#[test]
+fn deriving_from_a_full_signal_is_an_error() {
+    let full = Signal::new(1);
+    for _ in 0..MAX_LISTENERS {
+        full.try_subscribe(|_| {}).unwrap();
+    }
+    assert!(try_derived(&full, |n| n * 2).is_err());
+
+    // Nothing is left subscribed to the dependency that had room
+    let other = Signal::new(2);
+    assert!(try_derived2((&other, &full), |(a, b)| a + b).is_err());
+    for _ in 0..MAX_LISTENERS {
+        other.try_subscribe(|_| {}).unwrap();
+    }
+}
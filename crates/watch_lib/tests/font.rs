@@ -0,0 +1,145 @@
+mod common;
+
+use common::{new_context, pixel, rect, text};
+use watch_lib::{
+    BoundingRect, ElementId, Font, Font8x8, Layout, ROW_BYTES, RectUIElement, Signal, TRANSPARENT,
+    TextStyle, TextUIElement, UIContext, Wrap,
+};
+
+// Solid blocks for every character but space, so exact pixels can be checked
+struct Blocks {
+    advance: u8,
+    width: u8,
+}
+
+impl Font for Blocks {
+    fn height(&self) -> u8 {
+        5
+    }
+    fn baseline(&self) -> u8 {
+        5
+    }
+    fn advance(&self, _c: char) -> u8 {
+        self.advance
+    }
+    fn glyph_width(&self, _c: char) -> u8 {
+        self.width
+    }
+    fn glyph_row(&self, c: char, _y: u8) -> u32 {
+        if c == ' ' {
+            0
+        } else {
+            !(u32::MAX >> self.width)
+        }
+    }
+}
+
+static BLOCKS: Blocks = Blocks {
+    advance: 4,
+    width: 3,
+};
+// Each block overhangs the next one
+static OVERLAPPING_BLOCKS: Blocks = Blocks {
+    advance: 2,
+    width: 4,
+};
+
+fn row_pixels(ctx: &UIContext, y: usize, width: usize) -> Vec<bool> {
+    (0..width).map(|x| pixel(ctx, x, y)).collect()
+}
+
+#[test]
+fn font8x8_covers_more_than_ascii() {
+    for c in ['a', 'ä', 'ß', 'Ω', '─', '╬', '█', '░'] {
+        assert!(
+            (0..8).any(|y| Font8x8.glyph_row(c, y) != 0),
+            "{c} has no glyph"
+        );
+    }
+    assert!((0..8).all(|y| Font8x8.glyph_row('\u{4e00}', y) == 0));
+    assert_eq!(Font8x8.advance('ä'), 8);
+    assert_eq!(Font8x8.height(), 8);
+}
+
+#[test]
+fn each_element_draws_with_its_own_font() {
+    let mut ctx = new_context();
+    let blocky = text(&mut ctx, "ab", rect(0, 0, 20, 10), TextStyle::default());
+    ctx.set_font(blocky, &BLOCKS);
+    let default = text(&mut ctx, "ab", rect(0, 20, 16, 8), TextStyle::default());
+    ctx.handle_draw_requests();
+
+    let block_row = [true, true, true, false, true, true, true, false];
+    for y in 0..5 {
+        assert_eq!(row_pixels(&ctx, y, 8), block_row);
+    }
+    assert_eq!(row_pixels(&ctx, 5, 8), [false; 8]);
+
+    // The other element still uses the context's font
+    let mut expected = new_context();
+    let id = text(
+        &mut expected,
+        "ab",
+        rect(0, 20, 16, 8),
+        TextStyle::default(),
+    );
+    expected.handle_draw_requests();
+    assert_eq!(
+        ctx.get_screen_buffer()[20 * ROW_BYTES..28 * ROW_BYTES],
+        expected.get_screen_buffer()[20 * ROW_BYTES..28 * ROW_BYTES]
+    );
+    assert_eq!(ctx.get_rect(default), expected.get_rect(id));
+}
+
+#[test]
+fn overhanging_glyphs_do_not_erase_each_other() {
+    let mut ctx = new_context();
+    let id = text(&mut ctx, "abc", rect(0, 0, 20, 5), TextStyle::default());
+    ctx.set_font(id, &OVERLAPPING_BLOCKS);
+    ctx.handle_draw_requests();
+
+    let expected: Vec<bool> = (0..10).map(|x| x < 8).collect();
+    assert_eq!(row_pixels(&ctx, 0, 10), expected);
+}
+
+#[test]
+fn wrapping_and_sizing_use_the_font_metrics() {
+    let mut ctx = new_context();
+    let wrapped = TextStyle {
+        wrap: Wrap::Word,
+        ..TextStyle::default()
+    };
+    let id = text(&mut ctx, "ab cd", rect(0, 0, 12, 10), wrapped);
+    ctx.set_font(id, &BLOCKS);
+    ctx.handle_draw_requests();
+    // "cd" is on the second line, one font height down
+    assert!(pixel(&ctx, 0, 5));
+    assert!(!pixel(&ctx, 8, 0));
+
+    let column = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(rect(100, 100, 100, 100), TRANSPARENT),
+    );
+    ctx.set_layout(column, Some(Layout::column()));
+    let signal = Signal::new("abc".to_string());
+    let sized = ctx.mount(column, TextUIElement::new(&signal, BoundingRect::default()));
+    ctx.set_font(sized, &BLOCKS);
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(sized), Some(rect(0, 0, 12, 5)));
+}
+
+#[test]
+fn changing_the_font_repaints_the_element() {
+    let area = rect(3, 5, 60, 20);
+    let mut ctx = new_context();
+    let id = text(&mut ctx, "hello", area, TextStyle::default());
+    ctx.handle_draw_requests();
+    ctx.set_font(id, &BLOCKS);
+    ctx.handle_draw_requests();
+
+    let mut fresh = new_context();
+    let fresh_id = text(&mut fresh, "hello", area, TextStyle::default());
+    fresh.set_font(fresh_id, &BLOCKS);
+    fresh.handle_draw_requests();
+    assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+}
@@ -0,0 +1,157 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{
+    Bitmap, CompositeMode, ElementId, ImageUIElement, Layout, RectUIElement, TRANSPARENT, UIContext,
+};
+
+// A 10x3 box outline, with the padding bits past the right edge set to make sure they're ignored
+#[rustfmt::skip]
+const BOX: Bitmap<'static> = Bitmap {
+    width: 10,
+    height: 3,
+    data: &[
+        0b11111111, 0b11111111,
+        0b10000000, 0b01111111,
+        0b11111111, 0b11111111,
+    ],
+    mask: None,
+};
+
+// The same box with a see-through window in the middle row
+#[rustfmt::skip]
+const WINDOW: Bitmap<'static> = Bitmap {
+    mask: Some(&[
+        0b11111111, 0b11000000,
+        0b11110000, 0b11000000,
+        0b11111111, 0b11000000,
+    ]),
+    ..BOX
+};
+
+#[rustfmt::skip]
+const DOT: Bitmap<'static> = Bitmap {
+    width: 2,
+    height: 2,
+    data: &[0b11000000, 0b11000000],
+    mask: None,
+};
+
+fn row(ctx: &UIContext, x: usize, y: usize, width: usize) -> Vec<bool> {
+    (x..x + width).map(|x| pixel(ctx, x, y)).collect()
+}
+
+#[test]
+fn bitmaps_report_their_pixels() {
+    assert_eq!(BOX.row_bytes(), 2);
+    assert_eq!(BOX.get_pixel(8, 1), 0);
+    assert_eq!(BOX.get_pixel(9, 1), 1);
+    assert_eq!(BOX.get_pixel(10, 1), TRANSPARENT);
+    assert_eq!(WINDOW.get_pixel(3, 1), 0);
+    assert_eq!(WINDOW.get_pixel(4, 1), TRANSPARENT);
+    assert!(!Bitmap { height: 4, ..BOX }.is_complete());
+}
+
+#[test]
+fn draws_at_any_offset() {
+    let mut ctx = new_context();
+    let id = ctx.mount(ElementId::ROOT, ImageUIElement::new(BOX, 13, 20));
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(13, 20, 10, 3)));
+    // Nothing spills past the right edge, even though the padding bits are set
+    let (o, x) = (true, false);
+    assert_eq!(row(&ctx, 12, 20, 12), [x, o, o, o, o, o, o, o, o, o, o, x]);
+    assert_eq!(row(&ctx, 12, 21, 12), [x, o, x, x, x, x, x, x, x, x, o, x]);
+}
+
+#[test]
+fn masked_out_pixels_show_what_is_underneath() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 50, 10), 1));
+    ctx.mount(ElementId::ROOT, ImageUIElement::new(WINDOW, 5, 2));
+    ctx.handle_draw_requests();
+    // The opaque 0s in the middle row are the only cleared pixels
+    let (o, x) = (true, false);
+    assert_eq!(row(&ctx, 4, 3, 12), [o, o, x, x, x, o, o, o, o, x, o, o]);
+    assert_eq!(row(&ctx, 4, 2, 12), [true; 12]);
+}
+
+#[test]
+fn redraws_only_what_changed() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, ImageUIElement::new(WINDOW, 21, 30));
+    let cover = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 4, 4), 1));
+    ctx.handle_draw_requests();
+    // Sliding a rect across the image repaints the bits it leaves
+    for x in [18, 22, 26, 40] {
+        ctx.set_position(cover, x, 29);
+        ctx.handle_draw_requests();
+    }
+
+    let mut fresh = new_context();
+    fresh.mount(ElementId::ROOT, ImageUIElement::new(WINDOW, 21, 30));
+    fresh.mount(ElementId::ROOT, RectUIElement::new(rect(40, 29, 4, 4), 1));
+    fresh.handle_draw_requests();
+    assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+}
+
+#[test]
+fn composites_like_other_elements() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 16, 4), 1));
+    let id = ctx.mount(ElementId::ROOT, ImageUIElement::new(BOX, 0, 0));
+    ctx.set_composite_mode(id, CompositeMode::Xor);
+    ctx.handle_draw_requests();
+    let (o, x) = (true, false);
+    assert_eq!(row(&ctx, 0, 0, 11), [x, x, x, x, x, x, x, x, x, x, o]);
+    assert_eq!(row(&ctx, 0, 1, 11), [x, o, o, o, o, o, o, o, o, x, o]);
+}
+
+#[test]
+fn a_bigger_rect_is_transparent_past_the_image() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 20, 20), 1));
+    let id = ctx.mount(ElementId::ROOT, ImageUIElement::new(DOT, 0, 0));
+    ctx.set_rect(id, rect(0, 0, 10, 10));
+    ctx.set_composite_mode(id, CompositeMode::Xor);
+    ctx.handle_draw_requests();
+    assert_eq!(row(&ctx, 0, 0, 4), [false, false, true, true]);
+    assert_eq!(row(&ctx, 0, 2, 4), [true; 4]);
+}
+
+#[test]
+fn swapping_the_image_resizes_fitted_elements() {
+    let mut ctx = new_context();
+    let id = ctx.mount(ElementId::ROOT, ImageUIElement::new(BOX, 3, 3));
+    ctx.set_fits_content(id, true);
+    ctx.handle_draw_requests();
+    ctx.set_image(id, DOT);
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(3, 3, 2, 2)));
+
+    let mut fresh = new_context();
+    fresh.mount(ElementId::ROOT, ImageUIElement::new(DOT, 3, 3));
+    fresh.handle_draw_requests();
+    assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+}
+
+#[test]
+fn layouts_size_images_to_the_bitmap() {
+    let mut ctx = new_context();
+    let column = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(rect(0, 0, 100, 100), TRANSPARENT),
+    );
+    ctx.set_layout(column, Some(Layout::column()));
+    let first = ctx.mount(column, ImageUIElement::new(BOX, 0, 0));
+    let second = ctx.mount(column, ImageUIElement::new(DOT, 0, 0));
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(first), Some(rect(0, 0, 10, 3)));
+    assert_eq!(ctx.get_rect(second), Some(rect(0, 3, 2, 2)));
+}
+
+#[test]
+#[should_panic(expected = "bitmap is missing rows")]
+fn rejects_short_data() {
+    ImageUIElement::new(Bitmap { height: 4, ..BOX }, 0, 0);
+}
@@ -0,0 +1,232 @@
+#![cfg(feature = "std")]
+
+use watch_lib::image_file::{ImageError, ImageFile};
+use watch_lib::{Bitmap, Dither, ElementId, Font8x8, ImageUIElement, TRANSPARENT, UIContext};
+
+const ARROW: &[&str] = &[
+    "..##......",
+    ".####.....",
+    "##########",
+    "##########",
+    ".####.....",
+    "..##......",
+];
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(format!(
+        "{}/tests/images/{name}",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap()
+}
+
+fn load(name: &str) -> ImageFile {
+    ImageFile::parse(&read(name)).unwrap()
+}
+
+// '#' for set pixels, '.' for clear and ' ' for transparent
+fn picture(bitmap: Bitmap) -> Vec<String> {
+    (0..bitmap.height)
+        .map(|y| {
+            (0..bitmap.width)
+                .map(|x| match bitmap.get_pixel(x, y) {
+                    0 => '.',
+                    1 => '#',
+                    _ => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn reads_plain_and_raw_pbm() {
+    let plain = load("arrow.pbm");
+    assert_eq!((plain.width, plain.height), (10, 6));
+    assert_eq!(plain.mask, None);
+    assert_eq!(picture(plain.bitmap()), ARROW);
+    assert_eq!(&plain.data[..4], [0x30, 0x00, 0x78, 0x00]);
+    // The raw file has junk in its padding bits
+    assert_eq!(load("arrow_raw.pbm"), plain);
+}
+
+#[test]
+fn reads_xbm() {
+    assert_eq!(load("arrow.xbm"), load("arrow.pbm"));
+}
+
+#[test]
+fn reads_png_whatever_the_pixel_format() {
+    assert_eq!(load("arrow_gray.png"), load("arrow.pbm"));
+    assert_eq!(load("arrow_stored.png"), load("arrow.pbm"));
+
+    // Both leave the dots in the top and bottom rows see-through
+    let mut see_through: Vec<String> = ARROW.iter().map(|row| row.to_string()).collect();
+    for y in [0, 5] {
+        see_through[y] = see_through[y].replace('.', " ");
+    }
+    let palette = load("arrow_palette.png");
+    assert!(palette.mask.is_some());
+    assert_eq!(picture(palette.bitmap()), see_through);
+    assert_eq!(picture(load("arrow_rgba.png").bitmap()), see_through);
+}
+
+#[test]
+fn reads_compressed_png() {
+    let rings = load("rings.png");
+    assert_eq!((rings.width, rings.height), (121, 90));
+    assert_eq!(rings, load("rings.pbm"));
+}
+
+#[test]
+fn reports_bad_files() {
+    let error = |bytes: &[u8]| ImageFile::parse(bytes).unwrap_err();
+    assert_eq!(
+        error(b"P1\n300 2\n"),
+        ImageError {
+            message: "images can be at most 255 pixels across"
+        }
+    );
+    assert_eq!(error(b"P1\n2 2\n1 0 1").message, "PBM data is cut short");
+    assert_eq!(
+        error(b"P1\n2 1\n1 2").message,
+        "plain PBM pixels should be 0 or 1"
+    );
+    assert_eq!(error(b"GIF89a").message, "missing XBM width or height");
+
+    let png = read("arrow_gray.png");
+    // IHDR starts at byte 8, and the second IDAT at byte 66
+    let interlaced = edit_chunk(&png, 8, 28, 1);
+    assert_eq!(
+        error(&interlaced).message,
+        "interlaced PNGs aren't supported"
+    );
+    let mut corrupt = png.clone();
+    corrupt[28] = 1;
+    assert_eq!(error(&corrupt).message, "PNG chunk fails its CRC");
+    // The last byte of the height, so the pixels don't fit
+    let short = edit_chunk(&png, 8, 23, 3);
+    assert_eq!(
+        error(&short).message,
+        "PNG data is bigger than its header says"
+    );
+    // The end of the zlib checksum
+    let wrong_sum = edit_chunk(&png, 66, 95, png[95] ^ 1);
+    assert_eq!(error(&wrong_sum).message, "PNG data fails its checksum");
+    assert_eq!(
+        error(&png[..png.len() - 30]).message,
+        "PNG data is cut short"
+    );
+}
+
+#[test]
+fn dithers_pngs_when_loading() {
+    let rings = read("rings.png");
+    let thresholded = ImageFile::parse(&rings).unwrap();
+    assert_eq!(
+        ImageFile::parse_dithered(&rings, Dither::Threshold).unwrap(),
+        thresholded
+    );
+    let smooth = ImageFile::parse_dithered(&rings, Dither::FloydSteinberg).unwrap();
+    assert_ne!(smooth.data, thresholded.data);
+
+    // Only the pixels are dithered - the mask is still thresholded on opacity
+    let arrow = read("arrow_rgba.png");
+    let bayer = ImageFile::parse_dithered(&arrow, Dither::Bayer).unwrap();
+    assert_eq!(bayer.mask, ImageFile::parse(&arrow).unwrap().mask);
+    // And the 1bpp formats come through untouched
+    let pbm = read("arrow.pbm");
+    assert_eq!(
+        ImageFile::parse_dithered(&pbm, Dither::Atkinson).unwrap(),
+        ImageFile::parse(&pbm).unwrap()
+    );
+}
+
+// Sets a byte of a PNG and fixes up the CRC of the chunk starting at chunk
+fn edit_chunk(png: &[u8], chunk: usize, at: usize, value: u8) -> Vec<u8> {
+    let mut png = png.to_vec();
+    png[at] = value;
+    let len = u32::from_be_bytes(png[chunk..chunk + 4].try_into().unwrap()) as usize;
+    let mut crc = !0u32;
+    for &byte in &png[chunk + 4..chunk + 8 + len] {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    png[chunk + 8 + len..][..4].copy_from_slice(&(!crc).to_be_bytes());
+    png
+}
+
+#[test]
+fn writes_a_bitmap_const() {
+    let source = load("arrow.pbm").to_rust("ARROW");
+    assert!(source.contains("pub const ARROW: watch_lib::Bitmap<'static> = watch_lib::Bitmap {"));
+    assert!(source.contains("    width: 10,\n    height: 6,\n"));
+    // A row per line, so the picture shows
+    assert!(source.contains("        0b00110000, 0b00000000,\n        0b01111000, 0b00000000,\n"));
+    assert!(source.contains("    mask: None,\n"));
+
+    let masked = load("arrow_rgba.png").to_rust("ARROW");
+    assert!(masked.contains("    mask: Some(&[\n        0b00110000, 0b00000000,\n"));
+    assert!(masked.ends_with("    ]),\n};\n"));
+}
+
+#[test]
+fn loaded_images_draw_like_consts() {
+    let image = load("arrow_rgba.png");
+    let bitmap = image.bitmap();
+    assert_eq!(bitmap.get_pixel(0, 0), TRANSPARENT);
+    // Leaked, like the const the firmware would have
+    let data: &'static [u8] = image.data.clone().leak();
+    let mask: &'static [u8] = image.mask.clone().unwrap().leak();
+    let mut ctx = UIContext::new(&Font8x8);
+    ctx.mount(
+        ElementId::ROOT,
+        ImageUIElement::new(
+            Bitmap {
+                data,
+                mask: Some(mask),
+                ..bitmap
+            },
+            4,
+            4,
+        ),
+    );
+    ctx.handle_draw_requests();
+    let buffer = ctx.get_screen_buffer();
+    assert_eq!(buffer[6 * watch_lib::ROW_BYTES], 0x0F);
+    assert_eq!(buffer[6 * watch_lib::ROW_BYTES + 1], 0xFC);
+}
+
+#[test]
+fn screenshots_read_back_as_pbm() {
+    let mut ctx = UIContext::new(&Font8x8);
+    let image = load("arrow.pbm");
+    let data: &'static [u8] = image.data.clone().leak();
+    ctx.mount(
+        ElementId::ROOT,
+        ImageUIElement::new(
+            Bitmap {
+                width: image.width,
+                height: image.height,
+                data,
+                mask: None,
+            },
+            13,
+            7,
+        ),
+    );
+    ctx.handle_draw_requests();
+    let mut pbm = Vec::new();
+    ctx.write_pbm(&mut pbm).unwrap();
+    assert!(pbm.starts_with(b"P4\n200 200\n"));
+
+    let screenshot = ImageFile::from_pbm(&pbm).unwrap();
+    assert_eq!((screenshot.width, screenshot.height), (200, 200));
+    assert_eq!(screenshot.data, ctx.get_screen_buffer());
+    assert_eq!(
+        screenshot.bitmap().get_pixel(15, 9),
+        image.bitmap().get_pixel(2, 2)
+    );
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{
+    Align, BoundingRect, ElementId, Layout, RectUIElement, Signal, Size, Sizing, TRANSPARENT,
+    TextUIElement, UIContext,
+};
+
+fn container(
+    ctx: &mut UIContext,
+    parent: ElementId,
+    area: BoundingRect,
+    layout: Layout,
+) -> ElementId {
+    let id = ctx.mount(parent, RectUIElement::new(area, TRANSPARENT));
+    ctx.set_layout(id, Some(layout));
+    id
+}
+
+fn block(ctx: &mut UIContext, parent: ElementId, width: u8, height: u8) -> ElementId {
+    ctx.mount(parent, RectUIElement::new(rect(0, 0, width, height), 1))
+}
+
+#[test]
+fn rows_share_left_over_space_between_fill_children() {
+    let mut ctx = new_context();
+    let row = container(
+        &mut ctx,
+        ElementId::ROOT,
+        rect(0, 0, 100, 20),
+        Layout {
+            padding: 2,
+            spacing: 4,
+            vertical: Align::Center,
+            ..Layout::row()
+        },
+    );
+    let fit = block(&mut ctx, row, 10, 6);
+    let fixed = block(&mut ctx, row, 10, 6);
+    ctx.set_layout_sizing(
+        fixed,
+        Sizing {
+            width: Size::Fixed(20),
+            height: Size::Fill,
+        },
+    );
+    let fills = [block(&mut ctx, row, 0, 4), block(&mut ctx, row, 0, 4)];
+    for id in fills {
+        ctx.set_layout_sizing(
+            id,
+            Sizing {
+                width: Size::Fill,
+                height: Size::Fit,
+            },
+        );
+    }
+    ctx.handle_draw_requests();
+
+    assert_eq!(ctx.get_rect(fit), Some(rect(2, 7, 10, 6)));
+    assert_eq!(ctx.get_rect(fixed), Some(rect(16, 2, 20, 16)));
+    // 96 inner pixels, less 30 fixed and 12 of spacing, split two ways
+    assert_eq!(ctx.get_rect(fills[0]), Some(rect(40, 8, 27, 4)));
+    assert_eq!(ctx.get_rect(fills[1]), Some(rect(71, 8, 27, 4)));
+    assert!(pixel(&ctx, 97, 8));
+    assert!(!pixel(&ctx, 98, 8));
+}
+
+#[test]
+fn columns_and_stacks_align_their_children() {
+    let mut ctx = new_context();
+    let column = container(
+        &mut ctx,
+        ElementId::ROOT,
+        rect(0, 0, 40, 40),
+        Layout {
+            spacing: 2,
+            horizontal: Align::Center,
+            vertical: Align::End,
+            ..Layout::column()
+        },
+    );
+    let top = block(&mut ctx, column, 10, 10);
+    let bottom = block(&mut ctx, column, 20, 10);
+    let stack = container(
+        &mut ctx,
+        ElementId::ROOT,
+        rect(50, 0, 40, 40),
+        Layout {
+            padding: 4,
+            horizontal: Align::End,
+            vertical: Align::Center,
+            ..Layout::stack()
+        },
+    );
+    let back = block(&mut ctx, stack, 20, 20);
+    let front = block(&mut ctx, stack, 10, 10);
+    ctx.handle_draw_requests();
+
+    assert_eq!(ctx.get_rect(top), Some(rect(15, 18, 10, 10)));
+    assert_eq!(ctx.get_rect(bottom), Some(rect(10, 30, 20, 10)));
+    assert_eq!(ctx.get_rect(back), Some(rect(16, 10, 20, 20)));
+    assert_eq!(ctx.get_rect(front), Some(rect(26, 15, 10, 10)));
+}
+
+#[test]
+fn layout_reruns_when_text_changes_length() {
+    let mut ctx = new_context();
+    let text = Signal::new("12".to_string());
+    let row = container(&mut ctx, ElementId::ROOT, rect(0, 0, 100, 8), Layout::row());
+    ctx.mount(row, TextUIElement::new(&text, rect(0, 0, 0, 0)));
+    let unit = block(&mut ctx, row, 4, 8);
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(unit), Some(rect(16, 0, 4, 8)));
+    assert!(pixel(&ctx, 16, 0));
+
+    text.set("1234".to_string());
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert_eq!(ctx.get_rect(unit), Some(rect(32, 0, 4, 8)));
+    assert!(pixel(&ctx, 32, 0));
+
+    text.set("1".to_string());
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(unit), Some(rect(8, 0, 4, 8)));
+    assert!(!pixel(&ctx, 32, 0));
+    assert!(!pixel(&ctx, 16, 0));
+}
+
+#[test]
+fn nested_containers_fit_their_content_and_hidden_children_take_no_space() {
+    let mut ctx = new_context();
+    let row = container(
+        &mut ctx,
+        ElementId::ROOT,
+        rect(0, 0, 100, 50),
+        Layout {
+            spacing: 1,
+            ..Layout::row()
+        },
+    );
+    let column = container(
+        &mut ctx,
+        row,
+        rect(0, 0, 0, 0),
+        Layout {
+            padding: 1,
+            spacing: 2,
+            ..Layout::column()
+        },
+    );
+    let wide = block(&mut ctx, column, 12, 5);
+    let narrow = block(&mut ctx, column, 6, 5);
+    let after = block(&mut ctx, row, 5, 5);
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(column), Some(rect(0, 0, 14, 14)));
+    assert_eq!(ctx.get_rect(narrow), Some(rect(1, 8, 6, 5)));
+    assert_eq!(ctx.get_rect(after), Some(rect(15, 0, 5, 5)));
+
+    ctx.set_visible(wide, false);
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(column), Some(rect(0, 0, 8, 7)));
+    assert_eq!(ctx.get_rect(narrow), Some(rect(1, 1, 6, 5)));
+    assert_eq!(ctx.get_rect(after), Some(rect(9, 0, 5, 5)));
+}
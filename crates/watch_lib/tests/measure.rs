@@ -0,0 +1,128 @@
+mod common;
+
+use common::{new_context, rect};
+use watch_lib::{
+    BoundingRect, ElementId, Font8x8, Layout, RectUIElement, Scale, Signal, Size, Sizing,
+    TRANSPARENT, TextSize, TextStyle, TextUIElement, Wrap, measure_text, text_lines,
+};
+
+fn size(width: i16, height: i16, line_count: usize) -> TextSize {
+    TextSize {
+        width,
+        height,
+        line_count,
+    }
+}
+
+#[test]
+fn measures_lines_of_text() {
+    let style = TextStyle::default();
+    assert_eq!(measure_text(&Font8x8, "hello", style, None), size(40, 8, 1));
+    let newlines = TextStyle {
+        newlines: true,
+        ..style
+    };
+    assert_eq!(
+        measure_text(&Font8x8, "a\nbcd", newlines, None),
+        size(24, 16, 2)
+    );
+    // Empty text still takes up a line's height
+    assert_eq!(measure_text(&Font8x8, "", style, None), size(0, 8, 0));
+}
+
+#[test]
+fn measures_scaled_text() {
+    let style = TextStyle {
+        scale: Scale { x: 3, y: 2 },
+        ..TextStyle::default()
+    };
+    assert_eq!(
+        measure_text(&Font8x8, "12:34", style, None),
+        size(120, 16, 1)
+    );
+}
+
+#[test]
+fn reports_where_wrapped_lines_break() {
+    let style = TextStyle {
+        wrap: Wrap::Word,
+        ..TextStyle::default()
+    };
+    let lines: Vec<_> = text_lines(&Font8x8, "ab cd ef", style, Some(40))
+        .map(|line| (line.range, line.width))
+        .collect();
+    assert_eq!(lines, [(0..5, 40), (6..8, 16)]);
+    assert_eq!(
+        measure_text(&Font8x8, "ab cd ef", style, Some(40)),
+        size(40, 16, 2)
+    );
+    // Without a width there's nothing to wrap at
+    assert_eq!(
+        measure_text(&Font8x8, "ab cd ef", style, None),
+        size(64, 8, 1)
+    );
+    // Even when it's wider than an i16 can count
+    let long = "ab ".repeat(2000);
+    assert_eq!(
+        measure_text(&Font8x8, &long, style, None),
+        size(i16::MAX, 8, 1)
+    );
+}
+
+#[test]
+fn context_measures_in_its_font() {
+    let ctx = new_context();
+    let style = TextStyle::default();
+    assert_eq!(
+        ctx.measure_text("10:42", style, None),
+        measure_text(ctx.font(), "10:42", style, None)
+    );
+}
+
+#[test]
+fn fitted_text_follows_its_content() {
+    let mut ctx = new_context();
+    let signal = Signal::new("12:34".to_string());
+    let id = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&signal, rect(10, 20, 0, 0)),
+    );
+    ctx.set_fits_content(id, true);
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(10, 20, 40, 8)));
+
+    signal.set("9:05".to_string());
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(10, 20, 32, 8)));
+
+    // The shrunk text repaints what it no longer covers
+    let mut fresh = new_context();
+    fresh.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&signal, rect(10, 20, 32, 8)),
+    );
+    fresh.handle_draw_requests();
+    assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+}
+
+#[test]
+fn layouts_size_fitted_children_themselves() {
+    let mut ctx = new_context();
+    let column = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(rect(0, 0, 100, 100), TRANSPARENT),
+    );
+    ctx.set_layout(column, Some(Layout::column()));
+    let signal = Signal::new("abc".to_string());
+    let id = ctx.mount(column, TextUIElement::new(&signal, BoundingRect::default()));
+    ctx.set_fits_content(id, true);
+    ctx.set_layout_sizing(
+        id,
+        Sizing {
+            width: Size::Fixed(50),
+            height: Size::Fit,
+        },
+    );
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(0, 0, 50, 8)));
+}
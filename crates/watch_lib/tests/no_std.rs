@@ -0,0 +1,45 @@
+use std::process::Command;
+
+const EMBEDDED_TARGET: &str = "thumbv7em-none-eabi";
+
+// The firmware builds the library without its std feature, so check that still compiles for the watch's
+// target, which also catches dependencies that quietly pull in std. Skipped, with a note, on machines
+// without the target.
+#[test]
+fn builds_without_std() {
+    if !target_installed(EMBEDDED_TARGET) {
+        eprintln!(
+            "skipping the no_std build: {EMBEDDED_TARGET} isn't installed - add it with rustup target add {EMBEDDED_TARGET}"
+        );
+        return;
+    }
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "check",
+            "--lib",
+            "--no-default-features",
+            "--target",
+            EMBEDDED_TARGET,
+        ])
+        .arg("--manifest-path")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(concat!(env!("CARGO_TARGET_TMPDIR"), "/no_std"))
+        .output()
+        .expect("cargo should run");
+    assert!(
+        output.status.success(),
+        "no_std build failed for {EMBEDDED_TARGET}:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn target_installed(target: &str) -> bool {
+    Command::new("rustc")
+        .args(["--print", "target-libdir", "--target", target])
+        .output()
+        .is_ok_and(|output| {
+            output.status.success()
+                && std::path::Path::new(String::from_utf8_lossy(&output.stdout).trim()).exists()
+        })
+}
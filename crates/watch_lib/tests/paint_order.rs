@@ -0,0 +1,82 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{ElementId, RectUIElement, Signal, TextUIElement, UIContext};
+
+#[test]
+fn later_siblings_paint_over_earlier_ones() {
+    let mut ctx = new_context();
+    let lower = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 20, 20), 1));
+    let upper = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(10, 10, 20, 20), 0));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 5, 5));
+    assert!(!pixel(&ctx, 15, 15));
+
+    assert!(ctx.bring_to_front(lower));
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert!(pixel(&ctx, 15, 15));
+    assert!(!pixel(&ctx, 25, 25));
+
+    assert!(ctx.send_to_back(lower));
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 15, 15));
+    assert_eq!(ctx.children(ElementId::ROOT).last(), Some(upper));
+}
+
+#[test]
+fn children_paint_over_their_parent_but_under_later_siblings_of_it() {
+    let mut ctx = new_context();
+    let card = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 40, 40), 0));
+    ctx.mount(card, RectUIElement::new(rect(0, 0, 30, 30), 1));
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(20, 20, 20, 20), 0));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 10, 10));
+    assert!(!pixel(&ctx, 25, 25));
+}
+
+#[test]
+fn z_index_overrides_sibling_order() {
+    let mut ctx = new_context();
+    let raised = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 20, 20), 1));
+    let sunk = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(10, 10, 20, 20), 0));
+    ctx.set_z_index(raised, 2);
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(12, 12, 2, 2), 0));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 15, 15));
+    assert!(pixel(&ctx, 12, 12));
+
+    ctx.set_z_index(sunk, 3);
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert!(!pixel(&ctx, 15, 15));
+    assert!(!pixel(&ctx, 12, 12));
+}
+
+fn mount_label(ctx: &mut UIContext, text: &Signal<String>) {
+    ctx.mount(ElementId::ROOT, TextUIElement::new(text, rect(0, 0, 16, 8)));
+    // Covers the right half of the second glyph
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(12, 0, 4, 8), 0));
+}
+
+#[test]
+fn partial_redraws_keep_overlapping_siblings_on_top() {
+    let mut ctx = new_context();
+    let text = Signal::new("AB".to_string());
+    mount_label(&mut ctx, &text);
+    ctx.handle_draw_requests();
+
+    text.set("MW".to_string());
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    for y in 0..8 {
+        for x in 12..16 {
+            assert!(!pixel(&ctx, x, y));
+        }
+    }
+
+    let mut expected = new_context();
+    mount_label(&mut expected, &Signal::new("MW".to_string()));
+    expected.handle_draw_requests();
+    assert_eq!(ctx.get_screen_buffer(), expected.get_screen_buffer());
+}
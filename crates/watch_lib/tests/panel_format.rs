@@ -0,0 +1,112 @@
+use watch_lib::{BoundingRect, PanelFormat, ROW_BYTES, Rotation, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const BUFFER_LEN: usize = ROW_BYTES * SCREEN_HEIGHT as usize;
+
+// Maps a screen point to where it should end up on the panel, given the screen's width and height
+type PointMapping = fn(usize, usize, usize, usize) -> (usize, usize);
+
+fn pixel(buffer: &[u8], x: usize, y: usize) -> bool {
+    buffer[y * ROW_BYTES + x / 8] & (0x80 >> (x % 8)) != 0
+}
+
+fn set_pixel(buffer: &mut [u8], x: usize, y: usize) {
+    buffer[y * ROW_BYTES + x / 8] |= 0x80 >> (x % 8);
+}
+
+// A few pixels that would land somewhere different under every rotation
+fn asymmetric_screen() -> Vec<u8> {
+    let mut buffer = vec![0; BUFFER_LEN];
+    for (x, y) in [
+        (0, 0),
+        (1, 0),
+        (7, 0),
+        (8, 3),
+        (199, 5),
+        (42, 199),
+        (100, 100),
+    ] {
+        set_pixel(&mut buffer, x, y);
+    }
+    buffer
+}
+
+#[test]
+fn inverts_polarity_for_panels_with_black_as_zero() {
+    let screen = asymmetric_screen();
+    let mut panel = vec![0; BUFFER_LEN];
+    PanelFormat {
+        invert: true,
+        rotation: Rotation::Deg0,
+    }
+    .convert(&screen, &mut panel);
+    // The top-left pixel is set, so it's black ink on the panel
+    assert_eq!(panel[0], 0b0011_1110);
+    for (panel_byte, screen_byte) in panel.iter().zip(&screen) {
+        assert_eq!(*panel_byte, !screen_byte);
+    }
+}
+
+#[test]
+fn rotations_move_pixels_clockwise() {
+    let screen = asymmetric_screen();
+    let w = SCREEN_WIDTH as usize;
+    let h = SCREEN_HEIGHT as usize;
+    let cases: [(Rotation, PointMapping); 4] = [
+        (Rotation::Deg0, |x, y, _, _| (x, y)),
+        (Rotation::Deg90, |x, y, _, h| (h - 1 - y, x)),
+        (Rotation::Deg180, |x, y, w, h| (w - 1 - x, h - 1 - y)),
+        (Rotation::Deg270, |x, y, w, _| (y, w - 1 - x)),
+    ];
+    for (rotation, expected_point) in cases {
+        let mut panel = vec![0; BUFFER_LEN];
+        PanelFormat {
+            invert: false,
+            rotation,
+        }
+        .convert(&screen, &mut panel);
+        for y in 0..h {
+            for x in 0..w {
+                let (px, py) = expected_point(x, y, w, h);
+                assert_eq!(
+                    pixel(&panel, px, py),
+                    pixel(&screen, x, y),
+                    "{rotation:?} at ({x}, {y})"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn converted_windows_stay_byte_aligned() {
+    let window = BoundingRect {
+        x: 16,
+        y: 3,
+        width: 8,
+        height: 10,
+    };
+    let format = |rotation| PanelFormat {
+        invert: true,
+        rotation,
+    };
+    assert_eq!(format(Rotation::Deg0).convert_window(&window), window);
+    assert_eq!(
+        format(Rotation::Deg180).convert_window(&window),
+        BoundingRect {
+            x: 176,
+            y: 187,
+            width: 8,
+            height: 10,
+        }
+    );
+    // Rows 3..13 become panel columns 187..197, which widen out to 184..200
+    assert_eq!(
+        format(Rotation::Deg90).convert_window(&window),
+        BoundingRect {
+            x: 184,
+            y: 16,
+            width: 16,
+            height: 8,
+        }
+    );
+}
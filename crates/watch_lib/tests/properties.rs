@@ -0,0 +1,134 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{
+    Bitmap, ElementId, Layout, RectUIElement, Shape, ShapeStyle, Signal, TextStyle, TextUIElement,
+};
+
+#[test]
+fn moving_a_parent_moves_and_erases_its_subtree() {
+    let mut ctx = new_context();
+    let card = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 10, 10), 1));
+    // Sits outside the card, so only the subtree invalidation can erase it
+    ctx.mount(card, RectUIElement::new(rect(20, 20, 4, 4), 1));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 21, 21));
+
+    assert!(ctx.set_position(card, 100, 100));
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert!(!pixel(&ctx, 5, 5));
+    assert!(!pixel(&ctx, 21, 21));
+    assert!(pixel(&ctx, 105, 105));
+    assert!(pixel(&ctx, 121, 121));
+}
+
+#[test]
+fn resizing_and_recoloring_repaint_the_old_area() {
+    let mut ctx = new_context();
+    let bar = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 40, 8), 1));
+    ctx.handle_draw_requests();
+
+    assert!(ctx.set_size(bar, 20, 8));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 19, 0));
+    assert!(!pixel(&ctx, 20, 0));
+
+    assert!(ctx.set_rect(bar, rect(50, 0, 10, 10)));
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 0, 0));
+    assert!(pixel(&ctx, 55, 5));
+
+    assert!(ctx.set_color(bar, 0));
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 55, 5));
+}
+
+#[test]
+fn hiding_reveals_what_is_underneath() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 20, 20), 1));
+    let cover = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(5, 5, 10, 10), 0));
+    let dot = ctx.mount(cover, RectUIElement::new(rect(2, 2, 2, 2), 1));
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 5, 5));
+    assert!(pixel(&ctx, 7, 7));
+
+    assert!(ctx.set_visible(cover, false));
+    assert!(!ctx.is_visible(cover));
+    // Still visible in its own right, just under a hidden parent
+    assert!(ctx.is_visible(dot));
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert!(pixel(&ctx, 5, 5));
+    assert!(pixel(&ctx, 7, 7));
+    assert!(pixel(&ctx, 9, 9));
+
+    // Hidden elements can still change, and show up as they are now
+    ctx.set_position(cover, 8, 8);
+    assert!(ctx.handle_draw_requests().regions.iter().all(|r| r.x >= 5));
+    assert!(pixel(&ctx, 9, 9));
+    assert!(ctx.set_visible(cover, true));
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 9, 9));
+    assert!(pixel(&ctx, 10, 10));
+}
+
+#[test]
+fn slots_of_unmounted_hidden_elements_come_back_visible() {
+    let mut ctx = new_context();
+    let old = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 8, 8), 1));
+    ctx.set_visible(old, false);
+    ctx.unmount(old);
+    assert!(!ctx.set_visible(old, true));
+    let new = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 8, 8), 1));
+    assert!(ctx.is_visible(new));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 0, 0));
+}
+
+#[test]
+fn setters_refuse_properties_the_element_doesnt_have() {
+    let mut ctx = new_context();
+    let value = Signal::new("hi".to_string());
+    let label = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&value, rect(0, 0, 16, 8)),
+    );
+    let card = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(20, 0, 10, 10), 1));
+    ctx.handle_draw_requests();
+
+    assert!(!ctx.set_color(label, 1));
+    assert!(!ctx.set_layout(label, Some(Layout::row())));
+    assert!(!ctx.set_clips_children(label, true));
+    assert!(!ctx.set_text_style(card, TextStyle::default()));
+    assert!(!ctx.set_fits_content(card, true));
+    assert!(!ctx.set_shape(
+        card,
+        Shape::Circle {
+            center: Default::default(),
+            radius: 2
+        }
+    ));
+    assert!(!ctx.set_shape_style(card, ShapeStyle::default()));
+    let dot = Bitmap {
+        width: 1,
+        height: 1,
+        data: &[0x80],
+        mask: None,
+    };
+    assert!(!ctx.set_image(card, dot));
+    assert!(ctx.handle_draw_requests().is_empty());
+    assert!(ctx.set_color(card, 0));
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 25, 5));
+}
+
+#[test]
+fn the_root_cant_be_changed() {
+    let mut ctx = new_context();
+    assert!(!ctx.set_layout(ElementId::ROOT, Some(Layout::row())));
+    assert!(!ctx.set_color(ElementId::ROOT, 1));
+    assert!(!ctx.set_rect(ElementId::ROOT, rect(0, 0, 10, 10)));
+    assert!(ctx.handle_draw_requests().is_empty());
+}
@@ -0,0 +1,314 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{
+    BoundingRect, ElementId, Point, RectUIElement, Shape, ShapeStyle, ShapeUIElement, TRANSPARENT,
+    UIContext, point_on_circle,
+};
+
+// The screen inside area, '#' for set pixels
+fn picture(ctx: &UIContext, area: BoundingRect) -> Vec<String> {
+    let (x, y) = (area.x as usize, area.y as usize);
+    (y..y + area.height as usize)
+        .map(|y| {
+            (x..x + area.width as usize)
+                .map(|x| if pixel(ctx, x, y) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+fn stroke(width: u8) -> ShapeStyle {
+    ShapeStyle {
+        stroke_width: width,
+        ..ShapeStyle::default()
+    }
+}
+
+fn filled() -> ShapeStyle {
+    ShapeStyle {
+        stroke: TRANSPARENT,
+        fill: 1,
+        ..ShapeStyle::default()
+    }
+}
+
+// Mounts the shape and returns it with the rect it ended up with
+fn draw(ctx: &mut UIContext, shape: Shape, style: ShapeStyle) -> (ElementId, BoundingRect) {
+    let id = ctx.mount(ElementId::ROOT, ShapeUIElement::new(shape, style));
+    ctx.handle_draw_requests();
+    (id, ctx.get_rect(id).unwrap())
+}
+
+#[test]
+fn draws_bresenham_lines() {
+    let mut ctx = new_context();
+    let line = Shape::Line {
+        from: Point::new(10, 4),
+        to: Point::new(15, 6),
+    };
+    let (_, area) = draw(&mut ctx, line, stroke(1));
+    assert_eq!(area, rect(10, 4, 6, 3));
+    assert_eq!(picture(&ctx, area), ["##....", "..##..", "....##"]);
+}
+
+#[test]
+fn thick_strokes_grow_the_rect() {
+    let mut ctx = new_context();
+    let line = Shape::Line {
+        from: Point::new(10, 10),
+        to: Point::new(14, 10),
+    };
+    // The brush reaches past the ends as well as either side
+    let (_, area) = draw(&mut ctx, line, stroke(3));
+    assert_eq!(area, rect(9, 9, 7, 3));
+    assert_eq!(picture(&ctx, area), ["#######"; 3]);
+    // Wider brushes are round
+    let dot = Shape::Line {
+        from: Point::new(50, 50),
+        to: Point::new(50, 50),
+    };
+    let (_, area) = draw(&mut ctx, dot, stroke(5));
+    assert_eq!(area, rect(48, 48, 5, 5));
+    assert_eq!(
+        picture(&ctx, area),
+        [".###.", "#####", "#####", "#####", ".###."]
+    );
+}
+
+#[test]
+fn strokes_and_fills_circles() {
+    let mut ctx = new_context();
+    let circle = |x| Shape::Circle {
+        center: Point::new(x, 13),
+        radius: 3,
+    };
+    let (_, outline) = draw(&mut ctx, circle(13), stroke(1));
+    assert_eq!(outline, rect(10, 10, 7, 7));
+    #[rustfmt::skip]
+    assert_eq!(picture(&ctx, outline), [
+        "..###..",
+        ".#...#.",
+        "#.....#",
+        "#.....#",
+        "#.....#",
+        ".#...#.",
+        "..###..",
+    ]);
+
+    // The fill reaches the outline without going past it
+    let (_, disc) = draw(&mut ctx, circle(33), filled());
+    #[rustfmt::skip]
+    assert_eq!(picture(&ctx, disc), [
+        "..###..",
+        ".#####.",
+        "#######",
+        "#######",
+        "#######",
+        ".#####.",
+        "..###..",
+    ]);
+}
+
+#[test]
+fn arcs_run_clockwise_from_twelve() {
+    let mut ctx = new_context();
+    let (center, radius) = (Point::new(20, 20), 5);
+    let arc = |start, end| Shape::Arc {
+        center,
+        radius,
+        start,
+        end,
+    };
+    // A quarter from 12 to 3 o'clock
+    let (id, _) = draw(&mut ctx, arc(0, 90), stroke(1));
+    assert!(pixel(&ctx, 20, 15));
+    assert!(pixel(&ctx, 25, 20));
+    assert!(pixel(&ctx, 24, 17));
+    assert!(!pixel(&ctx, 15, 20));
+    assert!(!pixel(&ctx, 20, 25));
+
+    // Three quarters, from 3 o'clock round to 12
+    ctx.set_shape(id, arc(90, 0));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 20, 25));
+    assert!(pixel(&ctx, 15, 20));
+    assert!(!pixel(&ctx, 24, 17));
+
+    // Filled, they're pie slices
+    ctx.set_shape(id, arc(180, 270));
+    ctx.set_shape_style(id, filled());
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 20, 20));
+    assert!(pixel(&ctx, 17, 23));
+    assert!(!pixel(&ctx, 23, 23));
+    assert!(!pixel(&ctx, 17, 17));
+}
+
+#[test]
+fn fills_polygons_without_overlapping_neighbours() {
+    let mut ctx = new_context();
+    let square = |x: i16| {
+        Shape::Polygon(vec![
+            Point::new(x, 0),
+            Point::new(x + 4, 0),
+            Point::new(x + 4, 4),
+            Point::new(x, 4),
+        ])
+    };
+    let (_, area) = draw(&mut ctx, square(0), filled());
+    assert_eq!(area, rect(0, 0, 5, 5));
+    // The right and bottom edges belong to whatever is next to it
+    assert_eq!(
+        picture(&ctx, area),
+        ["####.", "####.", "####.", "####.", "....."]
+    );
+
+    let (_, area) = draw(&mut ctx, square(10), stroke(1));
+    assert_eq!(
+        picture(&ctx, area),
+        ["#####", "#...#", "#...#", "#...#", "#####"]
+    );
+
+    // Outlines can cross themselves
+    let bow_tie = Shape::Polygon(vec![
+        Point::new(20, 0),
+        Point::new(26, 6),
+        Point::new(26, 0),
+        Point::new(20, 6),
+    ]);
+    let (_, area) = draw(&mut ctx, bow_tie, filled());
+    assert_eq!(
+        picture(&ctx, area),
+        [
+            ".......", "#....#.", "##..##.", "######.", "##..##.", "#....#.", "......."
+        ]
+    );
+}
+
+#[test]
+fn fills_polygons_reaching_far_off_screen() {
+    let mut ctx = new_context();
+    let sliver = Shape::Polygon(vec![
+        Point::new(0, 0),
+        Point::new(30000, 1000),
+        Point::new(0, 2000),
+    ]);
+    draw(&mut ctx, sliver, filled());
+    // The top edge crosses row 1 at x = 30
+    assert!(pixel(&ctx, 29, 1));
+    assert!(!pixel(&ctx, 30, 1));
+    assert!(pixel(&ctx, 150, 10));
+}
+
+#[test]
+fn rounded_rects_stroke_inside_their_rect() {
+    let mut ctx = new_context();
+    let shape = Shape::RoundedRect {
+        rect: rect(30, 30, 10, 6),
+        radius: 2,
+    };
+    let (_, area) = draw(&mut ctx, shape, stroke(1));
+    assert_eq!(area, rect(30, 30, 10, 6));
+    #[rustfmt::skip]
+    assert_eq!(picture(&ctx, area), [
+        ".########.",
+        "##......##",
+        "#........#",
+        "#........#",
+        "##......##",
+        ".########.",
+    ]);
+}
+
+#[test]
+fn stroke_and_fill_take_their_own_colors() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 20, 20), 1));
+    let shape = Shape::RoundedRect {
+        rect: rect(2, 2, 10, 6),
+        radius: 2,
+    };
+    let style = ShapeStyle {
+        stroke: 0,
+        stroke_width: 1,
+        fill: 1,
+    };
+    let (_, area) = draw(&mut ctx, shape, style);
+    #[rustfmt::skip]
+    assert_eq!(picture(&ctx, area), [
+        "#........#",
+        "..######..",
+        ".########.",
+        ".########.",
+        "..######..",
+        "#........#",
+    ]);
+}
+
+#[test]
+fn resized_rounded_rects_fill_the_element() {
+    let mut ctx = new_context();
+    let shape = |width, height| Shape::RoundedRect {
+        rect: rect(40, 40, width, height),
+        radius: 3,
+    };
+    let (id, _) = draw(&mut ctx, shape(10, 10), stroke(2));
+    ctx.set_rect(id, rect(40, 40, 30, 12));
+    ctx.handle_draw_requests();
+
+    let mut fresh = new_context();
+    draw(&mut fresh, shape(30, 12), stroke(2));
+    assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+}
+
+#[test]
+fn moving_hands_redraw_what_they_leave() {
+    let mut ctx = new_context();
+    let center = Point::new(100, 100);
+    let hand = |degrees| Shape::Line {
+        from: center,
+        to: point_on_circle(center, 60, degrees),
+    };
+    let (id, _) = draw(&mut ctx, hand(0), stroke(3));
+    for degrees in [6, 12, 90, 200] {
+        ctx.set_shape(id, hand(degrees));
+        ctx.handle_draw_requests();
+    }
+    // Moving the element takes the shape with it
+    ctx.set_position(id, 0, 0);
+    ctx.handle_draw_requests();
+    let moved = ctx.get_rect(id).unwrap();
+
+    let mut fresh = new_context();
+    let (fresh_id, _) = draw(&mut fresh, hand(200), stroke(3));
+    fresh.set_position(fresh_id, 0, 0);
+    fresh.handle_draw_requests();
+    assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+    assert_eq!((moved.x, moved.y), (0, 0));
+    assert!(ctx.get_screen_buffer().iter().any(|&byte| byte != 0));
+}
+
+#[test]
+fn restyling_keeps_the_element_where_it_was_moved() {
+    let mut ctx = new_context();
+    let line = Shape::Line {
+        from: Point::new(10, 10),
+        to: Point::new(20, 10),
+    };
+    let (id, _) = draw(&mut ctx, line, stroke(1));
+    ctx.set_position(id, 50, 60);
+    ctx.set_shape_style(id, stroke(3));
+    assert_eq!(ctx.get_rect(id), Some(rect(49, 59, 13, 3)));
+}
+
+#[test]
+fn finds_points_around_a_circle() {
+    let center = Point::new(100, 100);
+    assert_eq!(point_on_circle(center, 10, 0), Point::new(100, 90));
+    assert_eq!(point_on_circle(center, 10, 90), Point::new(110, 100));
+    assert_eq!(point_on_circle(center, 10, 180), Point::new(100, 110));
+    assert_eq!(point_on_circle(center, 10, -90), Point::new(90, 100));
+    assert_eq!(point_on_circle(center, 10, 45), Point::new(107, 93));
+    assert_eq!(point_on_circle(center, 10, 405), Point::new(107, 93));
+}
@@ -0,0 +1,34 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use watch_lib::{Observable, Signal, derived};
+
+#[test]
+fn unsubscribing_twice_leaves_the_slots_new_listener_alone() {
+    let signal = Signal::new(0);
+    let calls = Rc::new(Cell::new(0));
+    let old = signal.subscribe(|_| {});
+    signal.unsubscribe(old);
+    let counted = calls.clone();
+    let new = signal.subscribe(move |_| counted.set(counted.get() + 1));
+    assert_ne!(old, new);
+
+    signal.unsubscribe(old);
+    signal.set(1);
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn derived_signals_stop_notifying_once_unsubscribed() {
+    let signal = Signal::new(1);
+    let doubled = derived(&signal, |n| n * 2);
+    let seen = Rc::new(Cell::new(0));
+    let seen_by_listener = seen.clone();
+    let id = doubled.subscribe(move |n| seen_by_listener.set(n));
+    signal.set(2);
+    assert_eq!(seen.get(), 4);
+
+    doubled.unsubscribe(id);
+    signal.set(3);
+    assert_eq!(seen.get(), 4);
+    assert_eq!(doubled.peek(), 6);
+}
@@ -0,0 +1,203 @@
+// Elements paint whole bytes through draw_row. These check that gives the same screen as painting
+// them a pixel at a time through get_pixel, whatever the element, clip or composite mode.
+mod common;
+
+use common::rect;
+use watch_lib::{
+    Bitmap, BoundingRect, CapacityError, CompositeMode, ElementId, Font8x8, ImageUIElement, Point,
+    ROW_BYTES, RectUIElement, Shape, ShapeStyle, ShapeUIElement, Signal, TRANSPARENT,
+    TextUIElement, UIContext, UIElement,
+};
+
+// Hides the element's draw_row, so it's painted by the default get_pixel loop
+struct PerPixel<E>(E);
+
+impl<E: UIElement> UIElement for PerPixel<E> {
+    fn mount_to_context(&self, ctx: &UIContext, id: ElementId) -> Result<(), CapacityError> {
+        self.0.mount_to_context(ctx, id)
+    }
+    fn get_pixel(&self, ctx: &UIContext, x: u8, y: u8) -> u8 {
+        self.0.get_pixel(ctx, x, y)
+    }
+    fn get_bounding_rect(&self) -> BoundingRect {
+        self.0.get_bounding_rect()
+    }
+    fn set_bounding_rect(&mut self, rect: BoundingRect) -> bool {
+        self.0.set_bounding_rect(rect)
+    }
+    fn get_intrinsic_size(&self, ctx: &UIContext) -> Option<(u8, u8)> {
+        self.0.get_intrinsic_size(ctx)
+    }
+    fn supports_children(&self) -> bool {
+        self.0.supports_children()
+    }
+    fn get_first_child_id(&self) -> Option<ElementId> {
+        self.0.get_first_child_id()
+    }
+    fn set_first_child_id(&mut self, id: Option<ElementId>) {
+        self.0.set_first_child_id(id)
+    }
+    fn get_next_element_id(&self) -> Option<ElementId> {
+        self.0.get_next_element_id()
+    }
+    fn set_next_element_id(&mut self, id: Option<ElementId>) {
+        self.0.set_next_element_id(id)
+    }
+    fn insert_child_at_end(&mut self, ctx: &mut UIContext, id: ElementId) {
+        self.0.insert_child_at_end(ctx, id)
+    }
+    fn get_clips_children(&self) -> bool {
+        self.0.get_clips_children()
+    }
+    fn set_clips_children(&mut self, clips_children: bool) -> bool {
+        self.0.set_clips_children(clips_children)
+    }
+    fn get_composite_mode(&self) -> CompositeMode {
+        self.0.get_composite_mode()
+    }
+    fn set_composite_mode(&mut self, mode: CompositeMode) -> bool {
+        self.0.set_composite_mode(mode)
+    }
+    fn unmount_from_context(&self, ctx: &UIContext) {
+        self.0.unmount_from_context(ctx)
+    }
+}
+
+#[rustfmt::skip]
+const BADGE: Bitmap<'static> = Bitmap {
+    width: 11,
+    height: 4,
+    data: &[
+        0b10101010, 0b10100000,
+        0b01010101, 0b01000000,
+        0b11110000, 0b11100000,
+        0b00001111, 0b00000000,
+    ],
+    mask: Some(&[
+        0b01111111, 0b11000000,
+        0b11111111, 0b11100000,
+        0b11111111, 0b11100000,
+        0b01111111, 0b11000000,
+    ]),
+};
+
+const MODES: [CompositeMode; 4] = [
+    CompositeMode::Normal,
+    CompositeMode::Or,
+    CompositeMode::Xor,
+    CompositeMode::Mask,
+];
+
+struct Scene {
+    ctx: UIContext,
+    per_pixel: bool,
+    // Shown by every text element. A signal only has room for so many listeners, so each scene has its own.
+    text: Signal<String>,
+    // Elements the partial repaint test moves about
+    moving: Vec<ElementId>,
+}
+
+impl Scene {
+    // A striped backdrop, then a panel per composite mode with every kind of element inside, all off byte
+    // boundaries and hanging over the panel's edges. Every other panel clips them.
+    fn new(per_pixel: bool, text: &str) -> Scene {
+        let mut scene = Scene {
+            ctx: UIContext::new(&Font8x8),
+            per_pixel,
+            text: Signal::new(text.to_string()),
+            moving: Vec::new(),
+        };
+        let text = scene.text.clone();
+        for i in 0..20 {
+            scene.mount(
+                ElementId::ROOT,
+                RectUIElement::new(rect(i * 10 + 3, 0, 5, 200), 1),
+            );
+        }
+        for (i, mode) in MODES.into_iter().enumerate() {
+            let panel = scene.mount(
+                ElementId::ROOT,
+                RectUIElement::new(
+                    rect(11 + i as i16 * 3, 5 + i as i16 * 48, 173, 43),
+                    TRANSPARENT,
+                ),
+            );
+            scene.ctx.set_clips_children(panel, i % 2 == 0);
+            let circle = Shape::Circle {
+                center: Point::new(131, 22),
+                radius: 19,
+            };
+            let style = ShapeStyle {
+                stroke: 1,
+                stroke_width: 3,
+                fill: 0,
+            };
+            let children = [
+                scene.mount(panel, RectUIElement::new(rect(-4, 3, 29, 13), 1)),
+                scene.mount(panel, RectUIElement::new(rect(21, 9, 19, 30), 0)),
+                scene.mount(panel, TextUIElement::new(&text, rect(37, 2, 75, 17))),
+                scene.mount(panel, ImageUIElement::new(BADGE, 165, 37)),
+                scene.mount(panel, ShapeUIElement::new(circle, style)),
+            ];
+            for id in children {
+                scene.ctx.set_composite_mode(id, mode);
+            }
+            scene.moving.push(children[3]);
+        }
+        // Hanging off the edges of the screen
+        scene.mount(
+            ElementId::ROOT,
+            RectUIElement::new(rect(-6, 193, 19, 12), 1),
+        );
+        scene.mount(ElementId::ROOT, ImageUIElement::new(BADGE, 191, -2));
+        scene
+    }
+
+    fn mount(&mut self, parent: ElementId, el: impl UIElement + 'static) -> ElementId {
+        if self.per_pixel {
+            self.ctx.mount(parent, PerPixel(el))
+        } else {
+            self.ctx.mount(parent, el)
+        }
+    }
+}
+
+fn assert_same(spans: &UIContext, pixels: &UIContext) {
+    let (spans, pixels) = (spans.get_screen_buffer(), pixels.get_screen_buffer());
+    for (i, (a, b)) in spans.iter().zip(pixels).enumerate() {
+        let (x, y) = (i % ROW_BYTES * 8, i / ROW_BYTES);
+        assert_eq!(a, b, "bytes differ at ({x}, {y}): {a:08b} vs {b:08b}");
+    }
+}
+
+#[test]
+fn spans_paint_the_same_as_pixels() {
+    let text = "Tick tock, wrap me round";
+    let mut spans = Scene::new(false, text);
+    let mut pixels = Scene::new(true, text);
+    spans.ctx.handle_draw_requests();
+    pixels.ctx.handle_draw_requests();
+    assert_same(&spans.ctx, &pixels.ctx);
+}
+
+#[test]
+fn partial_repaints_match_too() {
+    let mut spans = Scene::new(false, "12:00");
+    let mut pixels = Scene::new(true, "12:00");
+    spans.ctx.handle_draw_requests();
+    pixels.ctx.handle_draw_requests();
+
+    for step in 1..4i16 {
+        for scene in [&mut spans, &mut pixels] {
+            for &id in &scene.moving {
+                let rect = scene.ctx.get_rect(id).unwrap();
+                scene.ctx.set_position(id, rect.x + step * 3, rect.y - step);
+            }
+            scene.text.set(format!("12:0{step}"));
+        }
+        let report = spans.ctx.handle_draw_requests();
+        assert!(!report.full_refresh);
+        pixels.ctx.handle_draw_requests();
+        assert_same(&spans.ctx, &pixels.ctx);
+    }
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use common::{new_context, pixel, rect, text};
+use watch_lib::{
+    Align, BoundingRect, ElementId, Layout, RectUIElement, Signal, TRANSPARENT, TextStyle,
+    TextUIElement, UIContext, Wrap,
+};
+
+// Renders each (text, x, y) as a plain single line, to compare multi-line text against
+fn single_lines(lines: &[(&str, i16, i16)]) -> UIContext {
+    let mut ctx = new_context();
+    for &(value, x, y) in lines {
+        let width = (value.len() * 8) as u8;
+        text(&mut ctx, value, rect(x, y, width, 8), TextStyle::default());
+    }
+    ctx.handle_draw_requests();
+    ctx
+}
+
+fn assert_same_pixels(actual: &UIContext, expected: &UIContext, area: BoundingRect) {
+    for y in area.y..area.y + area.height as i16 {
+        for x in area.x..area.x + area.width as i16 {
+            let (x, y) = (x as usize, y as usize);
+            assert_eq!(
+                pixel(actual, x, y),
+                pixel(expected, x, y),
+                "pixel ({x}, {y}) differs"
+            );
+        }
+    }
+}
+
+fn wrapped() -> TextStyle {
+    TextStyle {
+        wrap: Wrap::Word,
+        ..TextStyle::default()
+    }
+}
+
+#[test]
+fn wraps_between_words() {
+    let mut ctx = new_context();
+    let area = rect(0, 0, 40, 24);
+    text(&mut ctx, "ab cd ef", area, wrapped());
+    ctx.handle_draw_requests();
+
+    let expected = single_lines(&[("ab cd", 0, 0), ("ef", 0, 8)]);
+    assert_same_pixels(&ctx, &expected, area);
+}
+
+#[test]
+fn breaks_words_too_long_for_a_line() {
+    let mut ctx = new_context();
+    let area = rect(0, 0, 24, 24);
+    text(&mut ctx, "abcdefgh", area, wrapped());
+    ctx.handle_draw_requests();
+
+    let expected = single_lines(&[("abc", 0, 0), ("def", 0, 8), ("gh", 0, 16)]);
+    assert_same_pixels(&ctx, &expected, area);
+}
+
+#[test]
+fn newlines_start_new_lines_only_when_enabled() {
+    let area = rect(0, 0, 40, 16);
+    let mut ctx = new_context();
+    text(
+        &mut ctx,
+        "ab\ncd",
+        area,
+        TextStyle {
+            newlines: true,
+            ..TextStyle::default()
+        },
+    );
+    ctx.handle_draw_requests();
+    assert_same_pixels(&ctx, &single_lines(&[("ab", 0, 0), ("cd", 0, 8)]), area);
+
+    let mut ctx = new_context();
+    text(&mut ctx, "ab\ncd", area, TextStyle::default());
+    ctx.handle_draw_requests();
+    assert_same_pixels(&ctx, &single_lines(&[("ab cd", 0, 0)]), area);
+}
+
+#[test]
+fn aligns_lines_within_the_element() {
+    let area = rect(0, 0, 40, 24);
+    let mut ctx = new_context();
+    text(
+        &mut ctx,
+        "ab",
+        area,
+        TextStyle {
+            horizontal: Align::Center,
+            vertical: Align::Center,
+            ..TextStyle::default()
+        },
+    );
+    ctx.handle_draw_requests();
+    assert_same_pixels(&ctx, &single_lines(&[("ab", 12, 8)]), area);
+
+    let mut ctx = new_context();
+    text(
+        &mut ctx,
+        "abc de",
+        area,
+        TextStyle {
+            wrap: Wrap::Word,
+            horizontal: Align::End,
+            vertical: Align::End,
+            ..TextStyle::default()
+        },
+    );
+    ctx.handle_draw_requests();
+    assert_same_pixels(&ctx, &single_lines(&[("abc", 16, 8), ("de", 24, 16)]), area);
+}
+
+#[test]
+fn ends_overflowing_text_with_an_ellipsis() {
+    let ellipsis = TextStyle {
+        ellipsis: true,
+        ..TextStyle::default()
+    };
+    let area = rect(0, 0, 40, 8);
+    let mut ctx = new_context();
+    text(&mut ctx, "abcdefgh", area, ellipsis);
+    ctx.handle_draw_requests();
+    assert_same_pixels(&ctx, &single_lines(&[("ab...", 0, 0)]), area);
+
+    // Lines that don't fit in the height are cut off, ending the last one that does
+    let area = rect(0, 0, 40, 16);
+    let mut ctx = new_context();
+    text(
+        &mut ctx,
+        "ab cd ef gh ij",
+        area,
+        TextStyle {
+            wrap: Wrap::Word,
+            ..ellipsis
+        },
+    );
+    ctx.handle_draw_requests();
+    assert_same_pixels(
+        &ctx,
+        &single_lines(&[("ab cd", 0, 0), ("ef...", 0, 8)]),
+        area,
+    );
+}
+
+#[test]
+fn text_that_fits_gets_no_ellipsis() {
+    let area = rect(0, 0, 40, 8);
+    let mut ctx = new_context();
+    text(
+        &mut ctx,
+        "abcde",
+        area,
+        TextStyle {
+            ellipsis: true,
+            ..TextStyle::default()
+        },
+    );
+    ctx.handle_draw_requests();
+    assert_same_pixels(&ctx, &single_lines(&[("abcde", 0, 0)]), area);
+}
+
+#[test]
+fn intrinsic_size_covers_every_line() {
+    let mut ctx = new_context();
+    let column = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(rect(0, 0, 100, 100), TRANSPARENT),
+    );
+    ctx.set_layout(column, Some(Layout::column()));
+    let signal = Signal::new("ab\ncde".to_string());
+    let id = ctx.mount(
+        column,
+        TextUIElement::with_style(
+            &signal,
+            BoundingRect::default(),
+            TextStyle {
+                newlines: true,
+                ..TextStyle::default()
+            },
+        ),
+    );
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(0, 0, 24, 16)));
+}
+
+#[test]
+fn changing_the_style_repaints_the_element() {
+    let area = rect(0, 0, 40, 24);
+    let mut ctx = new_context();
+    let id = text(&mut ctx, "ab cd ef", area, TextStyle::default());
+    ctx.handle_draw_requests();
+
+    assert!(ctx.set_text_style(id, wrapped()));
+    ctx.handle_draw_requests();
+    assert_same_pixels(&ctx, &single_lines(&[("ab cd", 0, 0), ("ef", 0, 8)]), area);
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::{new_context, rect};
+use watch_lib::{
+    BoundingRect, ElementId, Scale, Signal, TextStyle, TextUIElement, UIContext, Wrap,
+};
+
+fn wrapped() -> TextStyle {
+    TextStyle {
+        wrap: Wrap::Word,
+        ..TextStyle::default()
+    }
+}
+
+fn assert_matches_fresh(ctx: &UIContext, text: &str, area: BoundingRect, style: TextStyle) {
+    let signal = Signal::new(text.to_string());
+    let mut fresh = new_context();
+    fresh.mount(
+        ElementId::ROOT,
+        TextUIElement::with_style(&signal, area, style),
+    );
+    fresh.handle_draw_requests();
+    assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+}
+
+#[test]
+fn redraws_when_the_text_changes() {
+    let area = rect(5, 5, 80, 40);
+    let signal = Signal::new("first words".to_string());
+    let mut ctx = new_context();
+    ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::with_style(&signal, area, wrapped()),
+    );
+    ctx.handle_draw_requests();
+
+    for text in ["second", "a third longer run of words", ""] {
+        signal.set(text.to_string());
+        ctx.handle_draw_requests();
+        assert_matches_fresh(&ctx, text, area, wrapped());
+    }
+}
+
+#[test]
+fn text_changed_before_mounting_is_drawn() {
+    let signal = Signal::new("old".to_string());
+    let element = TextUIElement::new(&signal, rect(0, 0, 40, 8));
+    signal.set("new".to_string());
+
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, element);
+    ctx.handle_draw_requests();
+    assert_matches_fresh(&ctx, "new", rect(0, 0, 40, 8), TextStyle::default());
+}
+
+#[test]
+fn rewraps_when_resized_but_not_when_moved() {
+    let signal = Signal::new("ab cd ef gh".to_string());
+    let mut ctx = new_context();
+    let id = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::with_style(&signal, rect(0, 0, 40, 32), wrapped()),
+    );
+    ctx.handle_draw_requests();
+
+    ctx.set_size(id, 24, 32);
+    ctx.handle_draw_requests();
+    assert_matches_fresh(&ctx, "ab cd ef gh", rect(0, 0, 24, 32), wrapped());
+
+    ctx.set_position(id, 100, 50);
+    ctx.handle_draw_requests();
+    assert_matches_fresh(&ctx, "ab cd ef gh", rect(100, 50, 24, 32), wrapped());
+}
+
+#[test]
+fn redraws_when_the_style_changes() {
+    let signal = Signal::new("12:34".to_string());
+    let area = rect(0, 0, 120, 40);
+    let mut ctx = new_context();
+    let id = ctx.mount(ElementId::ROOT, TextUIElement::new(&signal, area));
+    ctx.handle_draw_requests();
+
+    let style = TextStyle {
+        scale: Scale::uniform(3),
+        ..TextStyle::default()
+    };
+    ctx.set_text_style(id, style);
+    ctx.handle_draw_requests();
+    assert_matches_fresh(&ctx, "12:34", area, style);
+
+    // The cached intrinsic size follows the text too
+    ctx.set_fits_content(id, true);
+    signal.set("1:05".to_string());
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(0, 0, 96, 24)));
+}
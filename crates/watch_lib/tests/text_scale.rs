@@ -0,0 +1,118 @@
+mod common;
+
+use common::{new_context, pixel, rect, text};
+use watch_lib::{
+    BoundingRect, ElementId, Layout, RectUIElement, Scale, Signal, TRANSPARENT, TextStyle,
+    TextUIElement, Wrap,
+};
+
+fn scaled(x: u8, y: u8) -> TextStyle {
+    TextStyle {
+        scale: Scale { x, y },
+        ..TextStyle::default()
+    }
+}
+
+// Every pixel of the scaled text should match the unscaled pixel it was blown up from
+fn assert_scaled(value: &str, style: TextStyle, width: u8, height: u8) {
+    let (scale_x, scale_y) = (style.scale.x, style.scale.y);
+    let mut small = new_context();
+    let unscaled = TextStyle {
+        scale: Scale::default(),
+        ..style
+    };
+    text(&mut small, value, rect(0, 0, width, height), unscaled);
+    small.handle_draw_requests();
+
+    let mut large = new_context();
+    let large_area = rect(0, 0, width * scale_x, height * scale_y);
+    text(&mut large, value, large_area, style);
+    large.handle_draw_requests();
+
+    for y in 0..large_area.height as usize {
+        for x in 0..large_area.width as usize {
+            assert_eq!(
+                pixel(&large, x, y),
+                pixel(&small, x / scale_x as usize, y / scale_y as usize),
+                "pixel ({x}, {y}) differs"
+            );
+        }
+    }
+}
+
+#[test]
+fn scales_glyphs_up_without_smoothing() {
+    for factor in 2..=6 {
+        let style = TextStyle {
+            scale: Scale::uniform(factor),
+            ..TextStyle::default()
+        };
+        assert_scaled("12:3", style, 32, 8);
+    }
+}
+
+#[test]
+fn scales_each_axis_separately() {
+    assert_scaled("ab", scaled(2, 4), 16, 8);
+    assert_scaled("ab", scaled(5, 1), 16, 8);
+}
+
+#[test]
+fn wraps_at_the_scaled_glyph_width() {
+    let style = TextStyle {
+        wrap: Wrap::Word,
+        ..scaled(3, 2)
+    };
+    assert_scaled("ab cd ef", style, 40, 16);
+}
+
+#[test]
+fn intrinsic_size_is_scaled() {
+    let mut ctx = new_context();
+    let column = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(rect(0, 0, 200, 200), TRANSPARENT),
+    );
+    ctx.set_layout(column, Some(Layout::column()));
+    let signal = Signal::new("abc".to_string());
+    let id = ctx.mount(
+        column,
+        TextUIElement::with_style(&signal, BoundingRect::default(), scaled(4, 3)),
+    );
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(0, 0, 96, 24)));
+
+    ctx.set_text_style(id, scaled(2, 2));
+    ctx.handle_draw_requests();
+    assert_eq!(ctx.get_rect(id), Some(rect(0, 0, 48, 16)));
+}
+
+#[test]
+fn changing_the_scale_redraws_the_old_and_new_glyphs() {
+    let area = rect(13, 21, 150, 60);
+    let mut ctx = new_context();
+    let id = text(&mut ctx, "10:42", area, scaled(1, 1));
+    ctx.handle_draw_requests();
+
+    for style in [scaled(4, 6), scaled(2, 3), scaled(1, 1)] {
+        ctx.set_text_style(id, style);
+        ctx.handle_draw_requests();
+
+        let mut fresh = new_context();
+        text(&mut fresh, "10:42", area, style);
+        fresh.handle_draw_requests();
+        assert_eq!(ctx.get_screen_buffer(), fresh.get_screen_buffer());
+    }
+}
+
+#[test]
+fn out_of_range_scales_are_clamped() {
+    let mut clamped = new_context();
+    text(&mut clamped, "ab", rect(0, 0, 200, 64), scaled(0, 9));
+    clamped.handle_draw_requests();
+
+    let mut expected = new_context();
+    text(&mut expected, "ab", rect(0, 0, 200, 64), scaled(1, 6));
+    expected.handle_draw_requests();
+    assert_eq!(clamped.get_screen_buffer(), expected.get_screen_buffer());
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{ElementId, RectUIElement, Signal, TextUIElement, UIContext};
+
+#[test]
+fn unmounting_erases_the_whole_subtree() {
+    let mut ctx = new_context();
+    let card = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(10, 10, 20, 20), 1));
+    let badge = ctx.mount(card, RectUIElement::new(rect(30, 0, 5, 5), 1));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 15, 15));
+    // The badge hangs outside its parent, so it has to be erased separately
+    assert!(pixel(&ctx, 42, 12));
+
+    assert!(ctx.unmount(card));
+    let report = ctx.handle_draw_requests();
+    assert!(!report.is_empty());
+    assert!(!pixel(&ctx, 15, 15));
+    assert!(!pixel(&ctx, 42, 12));
+    assert!(!ctx.unmount(badge));
+    assert!(!ctx.unmount(ElementId::ROOT));
+}
+
+#[test]
+fn unmounted_text_stops_listening() {
+    let mut ctx = new_context();
+    let text = Signal::new("on".to_string());
+    let label = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&text, rect(0, 0, 16, 8)),
+    );
+    ctx.handle_draw_requests();
+
+    ctx.unmount(label);
+    ctx.handle_draw_requests();
+    text.set("off".to_string());
+    assert!(ctx.handle_draw_requests().is_empty());
+}
+
+#[test]
+fn stale_ids_are_rejected_after_their_slot_is_reused() {
+    let mut ctx = new_context();
+    let old = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 8, 8), 1));
+    ctx.unmount(old);
+    let new = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(8, 0, 8, 8), 1));
+    assert_eq!(old.index(), new.index());
+    assert_ne!(old, new);
+    ctx.handle_draw_requests();
+
+    assert!(!ctx.unmount(old));
+    assert!(ctx.handle_draw_requests().is_empty());
+    assert!(pixel(&ctx, 8, 0));
+}
+
+fn mount_row(ctx: &mut UIContext, parent: ElementId, count: i16) -> Vec<ElementId> {
+    (0..count)
+        .map(|i| ctx.mount(parent, RectUIElement::new(rect(i * 10, 0, 5, 5), 1)))
+        .collect()
+}
+
+#[test]
+fn mounting_appends_every_child_in_order() {
+    let mut ctx = new_context();
+    let ids = mount_row(&mut ctx, ElementId::ROOT, 4);
+    assert_eq!(ctx.children(ElementId::ROOT).collect::<Vec<_>>(), ids);
+    ctx.handle_draw_requests();
+    for i in 0..4 {
+        assert!(pixel(&ctx, i * 10, 0));
+    }
+}
+
+#[test]
+fn siblings_can_be_reordered() {
+    let mut ctx = new_context();
+    let ids = mount_row(&mut ctx, ElementId::ROOT, 4);
+    let [a, b, c, d] = ids[..] else {
+        unreachable!()
+    };
+    let order = |ctx: &UIContext| ctx.children(ElementId::ROOT).collect::<Vec<_>>();
+
+    assert!(ctx.insert_before(d, b));
+    assert_eq!(order(&ctx), [a, d, b, c]);
+    assert!(ctx.insert_after(a, c));
+    assert_eq!(order(&ctx), [d, b, c, a]);
+    assert!(ctx.bring_to_front(d));
+    assert_eq!(order(&ctx), [b, c, a, d]);
+    assert!(ctx.send_to_back(a));
+    assert_eq!(order(&ctx), [a, b, c, d]);
+    // Already in place
+    assert!(ctx.insert_after(b, a));
+    assert_eq!(order(&ctx), [a, b, c, d]);
+
+    assert!(!ctx.insert_before(a, a));
+    assert!(!ctx.bring_to_front(ElementId::ROOT));
+    ctx.unmount(c);
+    assert!(!ctx.insert_after(a, c));
+    assert!(!ctx.send_to_back(c));
+    assert_eq!(order(&ctx), [a, b, d]);
+}
+
+#[test]
+fn reparenting_moves_the_subtree_on_screen() {
+    let mut ctx = new_context();
+    let left = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 50, 40, 40), 0));
+    let right = ctx.mount(
+        ElementId::ROOT,
+        RectUIElement::new(rect(100, 50, 40, 40), 0),
+    );
+    mount_row(&mut ctx, right, 2);
+    let card = ctx.mount(left, RectUIElement::new(rect(10, 10, 10, 10), 1));
+    let badge = ctx.mount(card, RectUIElement::new(rect(2, 2, 2, 2), 0));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 10, 60));
+    assert!(!pixel(&ctx, 12, 62));
+
+    assert!(ctx.reparent(card, right));
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert!(!pixel(&ctx, 10, 60));
+    assert!(pixel(&ctx, 110, 60));
+    assert!(!pixel(&ctx, 112, 62));
+    assert_eq!(ctx.children(left).count(), 0);
+    assert_eq!(ctx.children(right).last(), Some(card));
+    assert_eq!(ctx.children(card).collect::<Vec<_>>(), [badge]);
+
+    // An element can't be moved under itself
+    assert!(!ctx.reparent(card, badge));
+    assert!(!ctx.reparent(card, card));
+    assert!(!ctx.insert_before(right, badge));
+}
+
+#[test]
+fn adding_to_the_root_moves_a_mounted_element() {
+    let mut ctx = new_context();
+    let a = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 10, 10), 1));
+    let b = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(20, 0, 10, 10), 1));
+    let card = ctx.mount(b, RectUIElement::new(rect(5, 5, 5, 5), 0));
+    ctx.handle_draw_requests();
+
+    assert!(ctx.add_to_root(a));
+    assert_eq!(ctx.children(ElementId::ROOT).collect::<Vec<_>>(), [b, a]);
+    assert!(ctx.add_to_root(card));
+    assert_eq!(
+        ctx.children(ElementId::ROOT).collect::<Vec<_>>(),
+        [b, a, card]
+    );
+    assert_eq!(ctx.children(b).count(), 0);
+    // The card keeps its rect, which is now relative to the screen
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 7, 7));
+    assert!(pixel(&ctx, 27, 7));
+
+    ctx.unmount(card);
+    assert!(!ctx.add_to_root(card));
+}
+
+#[test]
+fn only_containers_take_children() {
+    let mut ctx = new_context();
+    let value = Signal::new("hi".to_string());
+    let label = ctx.mount(
+        ElementId::ROOT,
+        TextUIElement::new(&value, rect(0, 0, 16, 8)),
+    );
+    let card = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(20, 0, 10, 10), 1));
+
+    assert!(!ctx.reparent(card, label));
+    assert_eq!(
+        ctx.children(ElementId::ROOT).collect::<Vec<_>>(),
+        [label, card]
+    );
+    assert!(ctx.reparent(label, card));
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::{new_context, pixel, rect};
+use watch_lib::{ElementId, RectUIElement, Signal, derived};
+
+#[test]
+fn bound_elements_follow_their_observable() {
+    let mut ctx = new_context();
+    let charging = Signal::new(false);
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 16, 16), 1));
+    let icon = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(4, 4, 8, 8), 0));
+    ctx.mount(icon, RectUIElement::new(rect(2, 2, 4, 4), 1));
+    assert!(ctx.bind_visibility(icon, &charging));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 4, 4));
+
+    charging.set(true);
+    let report = ctx.handle_draw_requests();
+    assert!(!report.full_refresh);
+    assert!(!pixel(&ctx, 4, 4));
+    assert!(pixel(&ctx, 6, 6));
+
+    // Only the latest value counts
+    charging.set(false);
+    charging.set(true);
+    charging.set(false);
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 4, 4));
+    assert!(!ctx.is_visible(icon));
+}
+
+#[test]
+fn derived_values_can_drive_visibility() {
+    let mut ctx = new_context();
+    let battery = Signal::new(50);
+    let low = derived(&battery, |percent| percent < 20);
+    let badge = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 8, 8), 1));
+    ctx.bind_visibility(badge, &low);
+    ctx.handle_draw_requests();
+    assert!(!pixel(&ctx, 0, 0));
+
+    battery.set(10);
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 0, 0));
+}
+
+#[test]
+fn unmounting_or_unbinding_stops_following() {
+    let mut ctx = new_context();
+    let shown = Signal::new(true);
+    let first = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 8, 8), 1));
+    let second = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(8, 0, 8, 8), 1));
+    ctx.bind_visibility(first, &shown);
+    ctx.bind_visibility(second, &shown);
+    ctx.handle_draw_requests();
+
+    ctx.unbind_visibility(second);
+    shown.set(false);
+    ctx.unmount(first);
+    let replacement = ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 8, 8), 1));
+    ctx.handle_draw_requests();
+    assert!(ctx.is_visible(replacement));
+    assert!(ctx.is_visible(second));
+    assert!(pixel(&ctx, 0, 0));
+    assert!(pixel(&ctx, 8, 0));
+
+    shown.set(true);
+    assert!(ctx.handle_draw_requests().is_empty());
+}
+
+#[test]
+fn the_root_cant_be_hidden() {
+    let mut ctx = new_context();
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(0, 0, 10, 10), 1));
+    ctx.handle_draw_requests();
+    let shown = Signal::new(false);
+
+    assert!(!ctx.set_visible(ElementId::ROOT, false));
+    assert!(!ctx.bind_visibility(ElementId::ROOT, &shown));
+    assert!(ctx.is_visible(ElementId::ROOT));
+    ctx.handle_draw_requests();
+    assert!(pixel(&ctx, 5, 5));
+}
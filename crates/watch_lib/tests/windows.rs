@@ -0,0 +1,94 @@
+mod common;
+
+use common::{new_context, rect};
+use watch_lib::{BoundingRect, ElementId, RectUIElement, SCREEN_WIDTH, UIContext, WindowLimits};
+
+fn contains(window: &BoundingRect, x: i16, y: i16) -> bool {
+    (window.x..window.x + window.width as i16).contains(&x)
+        && (window.y..window.y + window.height as i16).contains(&y)
+}
+
+fn overlap(a: &BoundingRect, b: &BoundingRect) -> bool {
+    a.x < b.x + b.width as i16
+        && b.x < a.x + a.width as i16
+        && a.y < b.y + b.height as i16
+        && b.y < a.y + a.height as i16
+}
+
+// Dirties small rects scattered over the screen, none of them on a byte boundary
+fn scatter(ctx: &mut UIContext) {
+    for i in 0..12i16 {
+        let (x, y) = ((i * 53 + 3) % 190, (i * 37 + 5) % 190);
+        ctx.mount(ElementId::ROOT, RectUIElement::new(rect(x, y, 5, 3), 1));
+    }
+}
+
+fn check_windows(ctx: &mut UIContext, limits: WindowLimits) {
+    ctx.set_window_limits(limits);
+    scatter(ctx);
+    let report = ctx.handle_draw_requests();
+    let alignment = limits.column_alignment as i16;
+    assert!(report.regions.len() > limits.max_windows);
+    assert!(!report.aligned_regions.is_empty());
+    assert!(report.aligned_regions.len() <= limits.max_windows);
+    for (i, window) in report.aligned_regions.iter().enumerate() {
+        let right = window.x + window.width as i16;
+        assert_eq!(window.x % alignment, 0, "{window:?}");
+        assert!(
+            right % alignment == 0 || right == SCREEN_WIDTH as i16,
+            "{window:?}"
+        );
+        for other in &report.aligned_regions[i + 1..] {
+            assert!(!overlap(window, other), "{window:?} overlaps {other:?}");
+        }
+    }
+    for region in report.regions {
+        for y in region.y..region.y + region.height as i16 {
+            for x in region.x..region.x + region.width as i16 {
+                assert!(
+                    report.aligned_regions.iter().any(|w| contains(w, x, y)),
+                    "({x}, {y}) of {region:?} isn't in a window"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn scattered_changes_merge_into_few_byte_aligned_windows() {
+    for max_windows in [1, 2, 3, 5] {
+        check_windows(
+            &mut new_context(),
+            WindowLimits {
+                column_alignment: 8,
+                max_windows,
+            },
+        );
+    }
+}
+
+#[test]
+fn windows_follow_other_column_alignments() {
+    for column_alignment in [1, 4, 16] {
+        check_windows(
+            &mut new_context(),
+            WindowLimits {
+                column_alignment,
+                max_windows: 3,
+            },
+        );
+    }
+}
+
+#[test]
+fn one_window_spans_every_change() {
+    let mut ctx = new_context();
+    ctx.set_window_limits(WindowLimits {
+        column_alignment: 8,
+        max_windows: 1,
+    });
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(3, 10, 2, 2), 1));
+    ctx.mount(ElementId::ROOT, RectUIElement::new(rect(150, 90, 4, 4), 1));
+    let report = ctx.handle_draw_requests();
+    assert_eq!(report.aligned_regions, [rect(0, 10, 160, 84)]);
+}